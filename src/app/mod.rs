@@ -1,17 +1,21 @@
 //! UI orchestration for the wallpaper manager.
 
+mod refresh;
+
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
 
 use anyhow::Result;
+use chrono::{Local, TimeZone};
 use eframe::CreationContext;
 use eframe::egui::{
     self, Button, Color32, FontData, FontDefinitions, FontFamily, RichText, Stroke,
 };
 use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
 use tray_icon::{Icon, TrayIcon, TrayIconBuilder, TrayIconEvent};
 use windows::Win32::Foundation::{COLORREF, HWND};
 use windows::Win32::UI::WindowsAndMessaging::{
@@ -19,16 +23,34 @@ use windows::Win32::UI::WindowsAndMessaging::{
     SetLayeredWindowAttributes, SetWindowLongW, ShowWindow, WS_EX_LAYERED,
 };
 
+use crate::focus::{self, FocusMap, FocusPoint};
 use crate::i18n::{Language, Strings, strings};
+use crate::image_ops::collage::{MAX_COLLAGE_IMAGES, MIN_COLLAGE_IMAGES, collage_images};
+use crate::image_ops::layout::StitchLayout;
+use crate::image_ops::span::{span_bounds, span_images};
 use crate::image_ops::{
-    FolderSource, cached_wallpaper_path, collect_images, pick_random, process_image, stitch_images,
+    self, FolderSource, collect_images, process_image, sort_images, stitch_images,
+};
+use crate::ipc::{self, RemoteCommand};
+use crate::library::{self, MAX_RATING, SharedLibrary};
+use crate::monitor::process::ProcessMonitor;
+use crate::schedule::{self, cron::CronSchedule};
+use crate::session::{self, Pin, PinRequest};
+use crate::settings::{
+    self, AppSettings, BatteryPolicy, CropMode, DisplayRotation, IntervalMode, LetterboxFill,
+    RandomMode, ResampleFilter, RotateDirection, ScheduleRule, SortMode, SpanDisplay,
+    StitchOrientation, StitchScaling, ThemeMode, TimeAnchor,
 };
-use crate::settings::{self, AppSettings, StitchOrientation, ThemeMode};
-use crate::slideshow::{SlideshowEvent, SlideshowWorker};
+use crate::slideshow::preview::{self, DEFAULT_PREVIEW_COUNT, PlannedChange};
+use crate::slideshow::{PauseReason, SlideshowConfig, SlideshowEvent, SlideshowWorker, make_rng};
 use crate::startup;
-use crate::state::AppState;
+use crate::state::{AppState, ChangeTiming};
 use crate::theme::apply_theme;
-use crate::wallpaper::{StyleMode, set_wallpaper, set_wallpaper_style};
+use crate::wallpaper::{StyleMode, screen_size, set_wallpaper, set_wallpaper_style};
+use refresh::{Refresh, WallpaperRefresher};
+
+/// Longest side of the focal point preview, in pixels.
+const FOCUS_PREVIEW_SIZE: u32 = 360;
 
 /// Main application container that owns UI state and background workers.
 pub struct WallpaperApp {
@@ -57,6 +79,46 @@ pub struct WallpaperApp {
     folder_image_counts: Vec<usize>,
     /// True when the cached image list must be rebuilt from the current sources.
     index_dirty: bool,
+    /// Ratings and show counts shared with the slideshow worker.
+    library: SharedLibrary,
+    /// Source images of the wallpaper currently on the desktop.
+    current_images: Vec<PathBuf>,
+    /// Unix time of the worker's next planned change, while running.
+    next_change_at: Option<u64>,
+    /// Change time saved by the previous run, used once by the launch-time start.
+    pending_resume: Option<u64>,
+    /// Timing settings the running worker was started with.
+    worker_timing: Option<ChangeTiming>,
+    /// Hold on the current wallpaper, shared with the worker and persisted.
+    pin: Option<Pin>,
+    /// Why the worker paused rotation by itself, if it did.
+    paused: Option<PauseReason>,
+    /// Editable text of the pause-while-running list, one application per line.
+    pause_apps_text: String,
+    /// Number of changes the dry-run preview simulates.
+    preview_count: usize,
+    /// Result of the last dry-run preview; cleared when settings change.
+    preview: Vec<PlannedChange>,
+    /// Name typed into the stitch layout editor.
+    layout_name: String,
+    /// Text form of the layout being edited.
+    layout_spec: String,
+    /// Focal points of images, edited from the current wallpaper's sources.
+    focus: FocusMap,
+    /// Image whose focal point is being edited, if any.
+    focus_editor: Option<FocusEditor>,
+    /// Commands from the tray menu and the IPC listener.
+    remote_rx: Receiver<RemoteCommand>,
+    /// Re-renders the current wallpaper after style edits, off the UI thread.
+    refresher: WallpaperRefresher,
+}
+
+/// Preview of an image whose focal point is being picked.
+struct FocusEditor {
+    /// Source image being edited.
+    path: PathBuf,
+    /// Upright thumbnail shown for picking.
+    texture: egui::TextureHandle,
 }
 
 impl WallpaperApp {
@@ -73,13 +135,25 @@ impl WallpaperApp {
         }
 
         let state = AppState::from_settings(&settings);
+        let session = session::load();
         let status = strings(state.language).status_idle.to_string();
         apply_theme(&cc.egui_ctx, state.theme);
 
         let window_hwnd = window_hwnd_from_context(cc);
         let tray_icon = create_tray_icon(state.language);
         let tray_restore_requested = Arc::new(AtomicBool::new(false));
+        let (remote_tx, remote_rx) = mpsc::channel();
         if tray_icon.is_some() {
+            let menu_tx = remote_tx.clone();
+            let menu_ctx = cc.egui_ctx.clone();
+            thread::spawn(move || {
+                while let Ok(event) = MenuEvent::receiver().recv() {
+                    if let Some(command) = tray_menu_command(&event.id.0) {
+                        forward_remote(&menu_tx, &menu_ctx, command);
+                    }
+                }
+            });
+
             let restore_flag = Arc::clone(&tray_restore_requested);
             thread::spawn(move || {
                 loop {
@@ -102,10 +176,14 @@ impl WallpaperApp {
             });
         }
 
+        let ipc_ctx = cc.egui_ctx.clone();
+        let ipc_status = ipc::serve(move |command| forward_remote(&remote_tx, &ipc_ctx, command));
+
         let minimize_pending = settings.minimize_to_tray_on_start && started_from_startup;
         let change_once_on_startup = started_from_startup && settings.change_once_on_startup;
         let should_start = state.running && !change_once_on_startup;
 
+        let pause_apps_text = settings.pause_apps.join("\n");
         let mut app = Self {
             state,
             status,
@@ -119,7 +197,26 @@ impl WallpaperApp {
             indexed_images: Vec::new(),
             folder_image_counts: Vec::new(),
             index_dirty: true,
+            library: Arc::new(Mutex::new(library::load())),
+            current_images: session.current_paths(),
+            next_change_at: None,
+            pending_resume: should_start.then_some(session.next_change_at).flatten(),
+            worker_timing: None,
+            pin: session.pin.filter(|pin| pin.is_active(library::unix_now())),
+            remote_rx,
+            refresher: WallpaperRefresher::start(cc.egui_ctx.clone()),
+            paused: None,
+            pause_apps_text,
+            preview_count: DEFAULT_PREVIEW_COUNT,
+            preview: Vec::new(),
+            layout_name: String::new(),
+            layout_spec: String::new(),
+            focus: focus::load(),
+            focus_editor: None,
         };
+        if let Err(err) = ipc_status {
+            app.status = format!("Command listener unavailable: {err}");
+        }
 
         // Don't apply opacity here - defer to first frame for window to be ready
 
@@ -136,6 +233,7 @@ impl WallpaperApp {
             let _ = settings::save(&app.settings);
         } else if should_start {
             if let Err(err) = app.start_slideshow() {
+                app.pending_resume = None;
                 app.status = err.to_string();
                 app.state.running = false;
                 app.settings.running = false;
@@ -149,6 +247,7 @@ impl WallpaperApp {
     /// Render the application UI and react to user input.
     pub fn ui(&mut self, ctx: &egui::Context) {
         self.drain_events();
+        self.handle_remote_commands();
         self.handle_tray_events(ctx);
 
         let t = strings(self.state.language);
@@ -195,6 +294,21 @@ impl WallpaperApp {
                                 &mut restart_needed,
                             );
                             ui.separator();
+                            ui.label(
+                                RichText::new(loc(self.state.language, "Schedule", "排程"))
+                                    .strong(),
+                            );
+                            self.render_schedule_section(
+                                ui,
+                                &mut settings_changed,
+                                &mut restart_needed,
+                            );
+                            ui.separator();
+                            ui.label(
+                                RichText::new(loc(self.state.language, "Preview", "預覽")).strong(),
+                            );
+                            self.render_preview(ui);
+                            ui.separator();
                             ui.label(
                                 RichText::new(loc(self.state.language, "Appearance", "外觀"))
                                     .strong(),
@@ -227,6 +341,12 @@ impl WallpaperApp {
                         &mut restart_needed,
                     );
                     ui.separator();
+                    ui.label(RichText::new(loc(self.state.language, "Schedule", "排程")).strong());
+                    self.render_schedule_section(ui, &mut settings_changed, &mut restart_needed);
+                    ui.separator();
+                    ui.label(RichText::new(loc(self.state.language, "Preview", "預覽")).strong());
+                    self.render_preview(ui);
+                    ui.separator();
                     ui.label(
                         RichText::new(loc(self.state.language, "Appearance", "外觀")).strong(),
                     );
@@ -239,6 +359,8 @@ impl WallpaperApp {
         });
 
         if settings_changed {
+            // A preview of the old settings would be misleading.
+            self.preview.clear();
             if restart_needed {
                 self.restart_slideshow_if_running();
             }
@@ -268,6 +390,9 @@ impl WallpaperApp {
                             .strong()
                             .color(status_color),
                     );
+                    if let Some(text) = self.timing_text() {
+                        ui.label(text);
+                    }
                 }
             });
         });
@@ -365,6 +490,8 @@ impl WallpaperApp {
                     self.state.folders.push(FolderSource {
                         path,
                         include_subfolders: true,
+                        weight: 1,
+                        interval_secs: None,
                     });
                     self.mark_index_dirty();
                     *settings_changed = true;
@@ -382,6 +509,8 @@ impl WallpaperApp {
                         self.state.folders.push(FolderSource {
                             path,
                             include_subfolders: true,
+                            weight: 1,
+                            interval_secs: None,
                         });
                     }
                 }
@@ -494,14 +623,51 @@ impl WallpaperApp {
                             .small()
                             .weak(),
                     );
-                    if ui
-                        .checkbox(&mut folder.include_subfolders, t.include_subfolders)
-                        .changed()
-                    {
-                        source_flags_changed = true;
-                        *settings_changed = true;
-                        *restart_needed = true;
-                    }
+                    ui.horizontal_wrapped(|ui| {
+                        if ui
+                            .checkbox(&mut folder.include_subfolders, t.include_subfolders)
+                            .changed()
+                        {
+                            source_flags_changed = true;
+                            *settings_changed = true;
+                            *restart_needed = true;
+                        }
+                        ui.label(loc(self.state.language, "Weight", "權重"));
+                        if ui
+                            .add(egui::DragValue::new(&mut folder.weight).clamp_range(0..=100))
+                            .changed()
+                        {
+                            // Weights only affect selection, so the index stays valid.
+                            *settings_changed = true;
+                            *restart_needed = true;
+                        }
+                    });
+                    ui.horizontal_wrapped(|ui| {
+                        let mut own_interval = folder.interval_secs.is_some();
+                        if ui
+                            .checkbox(
+                                &mut own_interval,
+                                loc(self.state.language, "Own interval", "自訂間隔"),
+                            )
+                            .changed()
+                        {
+                            folder.interval_secs = own_interval.then_some(self.state.interval_secs);
+                            *settings_changed = true;
+                            *restart_needed = true;
+                        }
+                        if let Some(secs) = folder.interval_secs.as_mut()
+                            && ui
+                                .add(
+                                    egui::DragValue::new(secs)
+                                        .clamp_range(5..=86_400)
+                                        .suffix(" s"),
+                                )
+                                .changed()
+                        {
+                            *settings_changed = true;
+                            *restart_needed = true;
+                        }
+                    });
                     ui.separator();
                 }
                 if source_flags_changed {
@@ -555,6 +721,26 @@ impl WallpaperApp {
                 *settings_changed = true;
                 *restart_needed = true;
             }
+            let lang = self.state.language;
+            ui.add_enabled_ui(self.state.auto_rotate, |ui| {
+                egui::ComboBox::from_id_source("rotate_direction")
+                    .selected_text(rotate_direction_label(self.state.rotate_direction, lang))
+                    .show_ui(ui, |ui| {
+                        for direction in RotateDirection::ALL {
+                            if ui
+                                .selectable_value(
+                                    &mut self.state.rotate_direction,
+                                    direction,
+                                    rotate_direction_label(direction, lang),
+                                )
+                                .changed()
+                            {
+                                *settings_changed = true;
+                                *restart_needed = true;
+                            }
+                        }
+                    });
+            });
             if ui
                 .add_enabled(
                     !startup_once_mode,
@@ -567,14 +753,95 @@ impl WallpaperApp {
             }
         });
 
+        if self.state.random_order {
+            let mut mode_changed = false;
+            egui::ComboBox::from_label(loc(self.state.language, "Random mode", "隨機模式"))
+                .selected_text(random_mode_label(
+                    self.state.random_mode,
+                    self.state.language,
+                ))
+                .show_ui(ui, |ui| {
+                    for mode in RandomMode::ALL {
+                        if ui
+                            .selectable_value(
+                                &mut self.state.random_mode,
+                                mode,
+                                random_mode_label(mode, self.state.language),
+                            )
+                            .changed()
+                        {
+                            mode_changed = true;
+                        }
+                    }
+                });
+            if mode_changed {
+                *settings_changed = true;
+                *restart_needed = true;
+            }
+
+            if self.state.random_mode != RandomMode::Daily {
+                self.render_repeat_window(ui, settings_changed, restart_needed);
+            }
+
+            ui.horizontal_wrapped(|ui| {
+                let mut fixed_seed = self.state.seed.is_some();
+                if ui
+                    .checkbox(
+                        &mut fixed_seed,
+                        loc(self.state.language, "Fixed seed", "固定種子"),
+                    )
+                    .changed()
+                {
+                    self.state.seed = fixed_seed.then_some(0);
+                    *settings_changed = true;
+                    *restart_needed = true;
+                }
+                if let Some(seed) = self.state.seed.as_mut()
+                    && ui
+                        .add(egui::DragValue::new(seed).clamp_range(0..=u32::MAX as u64))
+                        .changed()
+                {
+                    *settings_changed = true;
+                    *restart_needed = true;
+                }
+            });
+        }
+
+        ui.add_enabled_ui(!self.state.random_order, |ui| {
+            let mut sort_changed = false;
+            egui::ComboBox::from_label(loc(self.state.language, "Sequential order", "循序排序"))
+                .selected_text(sort_label(self.state.sort_mode, self.state.language))
+                .show_ui(ui, |ui| {
+                    for mode in SortMode::ALL {
+                        if ui
+                            .selectable_value(
+                                &mut self.state.sort_mode,
+                                mode,
+                                sort_label(mode, self.state.language),
+                            )
+                            .changed()
+                        {
+                            sort_changed = true;
+                        }
+                    }
+                });
+            if sort_changed {
+                // The cached index carries the order, so rebuild it with the new sort.
+                self.mark_index_dirty();
+                *settings_changed = true;
+                *restart_needed = true;
+            }
+        });
+
         ui.add_space(6.0);
         ui.label(RichText::new(t.interval_seconds).strong());
+        let interval_enabled = !startup_once_mode && !self.state.cron_enabled;
         ui.horizontal_wrapped(|ui| {
             for (secs, label) in [(10_u64, "10s"), (30, "30s"), (60, "1m"), (300, "5m")] {
                 let selected = self.state.interval_secs == secs;
                 if ui
                     .add_enabled(
-                        !startup_once_mode,
+                        interval_enabled,
                         Button::new(label)
                             .fill(if selected {
                                 ui.visuals().selection.bg_fill
@@ -595,7 +862,7 @@ impl WallpaperApp {
         ui.horizontal(|ui| {
             if ui
                 .add_enabled(
-                    !startup_once_mode,
+                    interval_enabled,
                     egui::Slider::new(&mut self.state.interval_secs, 5..=7200)
                         .text(t.interval_seconds),
                 )
@@ -605,6 +872,24 @@ impl WallpaperApp {
                 *restart_needed = true;
             }
         });
+        self.render_interval_mode(ui, interval_enabled, settings_changed, restart_needed);
+        self.render_cron(ui, startup_once_mode, settings_changed, restart_needed);
+        self.render_battery_policy(ui, settings_changed, restart_needed);
+        self.render_pause_apps(ui, settings_changed);
+        if ui
+            .checkbox(
+                &mut self.state.change_on_resume,
+                loc(
+                    self.state.language,
+                    "Change wallpaper after waking from sleep",
+                    "從睡眠喚醒後更換桌布",
+                ),
+            )
+            .changed()
+        {
+            *settings_changed = true;
+            *restart_needed = true;
+        }
 
         ui.add_space(6.0);
         if ui
@@ -615,51 +900,155 @@ impl WallpaperApp {
             *restart_needed = true;
         }
 
-        if self.state.stitch_enabled {
+        let lang = self.state.language;
+        if ui
+            .checkbox(
+                &mut self.state.collage_enabled,
+                loc(lang, "Collage (photo wall)", "拼貼（照片牆）"),
+            )
+            .changed()
+        {
+            *settings_changed = true;
+            *restart_needed = true;
+        }
+
+        // A collage shares the output size and styling of stitched wallpapers.
+        let collage = self.state.collage_enabled;
+        if self.state.stitch_enabled || collage {
             egui::Grid::new("stitch_grid")
                 .num_columns(2)
                 .spacing(egui::vec2(8.0, 6.0))
                 .show(ui, |ui| {
-                    ui.label(t.stitch_count);
-                    let mut count = self.state.stitch_count as i32;
-                    if ui.add(egui::Slider::new(&mut count, 2..=5)).changed() {
-                        self.state.stitch_count = count as u8;
-                        *settings_changed = true;
-                        *restart_needed = true;
+                    if collage {
+                        ui.label(loc(lang, "Collage images", "拼貼張數"));
+                        if ui
+                            .add(egui::Slider::new(
+                                &mut self.state.collage_count,
+                                MIN_COLLAGE_IMAGES as u32..=MAX_COLLAGE_IMAGES as u32,
+                            ))
+                            .changed()
+                        {
+                            *settings_changed = true;
+                            *restart_needed = true;
+                        }
+                        ui.end_row();
+                    } else {
+                        ui.label(loc(self.state.language, "Layout", "版面"));
+                        self.render_layout_picker(ui, settings_changed, restart_needed);
+                        ui.end_row();
+
+                        // Count and orientation only pick the automatic layout.
+                        let automatic = self.state.stitch_layout.is_empty();
+                        ui.label(t.stitch_count);
+                        let mut count = self.state.stitch_count as i32;
+                        if ui
+                            .add_enabled(automatic, egui::Slider::new(&mut count, 2..=5))
+                            .changed()
+                        {
+                            self.state.stitch_count = count as u8;
+                            *settings_changed = true;
+                            *restart_needed = true;
+                        }
+                        ui.end_row();
+
+                        ui.label(t.stitch_orientation);
+                        let orientation_text = match self.state.stitch_orientation {
+                            StitchOrientation::Horizontal => t.stitch_horizontal,
+                            StitchOrientation::Vertical => t.stitch_vertical,
+                        };
+                        ui.add_enabled_ui(automatic, |ui| {
+                            egui::ComboBox::from_id_source("stitch_orientation_combo")
+                                .selected_text(orientation_text)
+                                .show_ui(ui, |ui| {
+                                    if ui
+                                        .selectable_value(
+                                            &mut self.state.stitch_orientation,
+                                            StitchOrientation::Horizontal,
+                                            t.stitch_horizontal,
+                                        )
+                                        .changed()
+                                    {
+                                        *settings_changed = true;
+                                        *restart_needed = true;
+                                    }
+                                    if ui
+                                        .selectable_value(
+                                            &mut self.state.stitch_orientation,
+                                            StitchOrientation::Vertical,
+                                            t.stitch_vertical,
+                                        )
+                                        .changed()
+                                    {
+                                        *settings_changed = true;
+                                        *restart_needed = true;
+                                    }
+                                });
+                        });
+                        ui.end_row();
+
+                        ui.label(loc(self.state.language, "Scaling", "縮放"));
+                        egui::ComboBox::from_id_source("stitch_scaling_combo")
+                            .selected_text(stitch_scaling_label(self.state.stitch_scaling, lang))
+                            .show_ui(ui, |ui| {
+                                for scaling in StitchScaling::ALL {
+                                    if ui
+                                        .selectable_value(
+                                            &mut self.state.stitch_scaling,
+                                            scaling,
+                                            stitch_scaling_label(scaling, lang),
+                                        )
+                                        .changed()
+                                    {
+                                        *settings_changed = true;
+                                        *restart_needed = true;
+                                    }
+                                }
+                            });
+                        ui.end_row();
                     }
+
+                    ui.label(loc(lang, "Gaps", "間距"));
+                    let mut style_changed = false;
+                    ui.horizontal_wrapped(|ui| {
+                        style_changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut self.state.stitch_gap)
+                                    .clamp_range(0..=200)
+                                    .prefix(loc(lang, "gap ", "間隔 "))
+                                    .suffix(" px"),
+                            )
+                            .changed();
+                        style_changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut self.state.stitch_margin)
+                                    .clamp_range(0..=400)
+                                    .prefix(loc(lang, "margin ", "外框 "))
+                                    .suffix(" px"),
+                            )
+                            .changed();
+                        style_changed |= ui
+                            .color_edit_button_srgb(&mut self.state.stitch_border_color)
+                            .changed();
+                    });
                     ui.end_row();
 
-                    ui.label(t.stitch_orientation);
-                    let orientation_text = match self.state.stitch_orientation {
-                        StitchOrientation::Horizontal => t.stitch_horizontal,
-                        StitchOrientation::Vertical => t.stitch_vertical,
-                    };
-                    egui::ComboBox::from_id_source("stitch_orientation_combo")
-                        .selected_text(orientation_text)
-                        .show_ui(ui, |ui| {
-                            if ui
-                                .selectable_value(
-                                    &mut self.state.stitch_orientation,
-                                    StitchOrientation::Horizontal,
-                                    t.stitch_horizontal,
-                                )
-                                .changed()
-                            {
-                                *settings_changed = true;
-                                *restart_needed = true;
-                            }
-                            if ui
-                                .selectable_value(
-                                    &mut self.state.stitch_orientation,
-                                    StitchOrientation::Vertical,
-                                    t.stitch_vertical,
-                                )
-                                .changed()
-                            {
-                                *settings_changed = true;
-                                *restart_needed = true;
-                            }
-                        });
+                    ui.label(loc(lang, "Corners", "圓角"));
+                    ui.horizontal_wrapped(|ui| {
+                        style_changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut self.state.stitch_corner_radius)
+                                    .clamp_range(0..=200)
+                                    .suffix(" px"),
+                            )
+                            .changed();
+                        style_changed |= ui
+                            .checkbox(&mut self.state.stitch_shadow, loc(lang, "Shadow", "陰影"))
+                            .changed();
+                    });
+                    if style_changed {
+                        *settings_changed = true;
+                        *restart_needed = true;
+                    }
                     ui.end_row();
 
                     ui.label(t.stitch_crop_width);
@@ -688,114 +1077,871 @@ impl WallpaperApp {
                     }
                     ui.end_row();
                 });
-        }
-    }
-
-    /// Render startup-related options (run on startup, minimize to tray).
-    fn render_startup_section(&mut self, ui: &mut egui::Ui, t: &Strings) {
-        ui.horizontal(|ui| {
-            if ui
-                .checkbox(&mut self.settings.run_on_startup, t.run_on_startup)
-                .changed()
-            {
-                let result = if self.settings.run_on_startup {
-                    startup::enable()
-                } else {
-                    startup::disable()
-                };
-                if let Err(err) = result {
-                    self.status = err.to_string();
-                    self.settings.run_on_startup = !self.settings.run_on_startup;
-                } else {
-                    if !self.settings.run_on_startup {
-                        self.settings.change_once_on_startup = false;
-                    }
-                    if let Err(err) = settings::save(&self.settings) {
-                        self.status = err.to_string();
-                    }
-                }
-            }
-            if self.settings.run_on_startup
-                && ui
-                    .checkbox(
-                        &mut self.settings.change_once_on_startup,
-                        t.change_once_on_startup,
-                    )
-                    .changed()
-            {
-                if self.settings.change_once_on_startup {
-                    self.state.running = false;
-                    self.settings.running = false;
-                    self.stop_worker();
-                }
-                if let Err(err) = settings::save(&self.settings) {
-                    self.status = err.to_string();
-                }
+            if !collage {
+                self.render_layout_editor(ui, settings_changed, restart_needed);
             }
-            if ui
-                .checkbox(
-                    &mut self.settings.minimize_to_tray_on_start,
-                    t.minimize_to_tray_on_start,
-                )
-                .changed()
-            {
-                if let Err(err) = settings::save(&self.settings) {
-                    self.status = err.to_string();
-                }
-            }
-        });
-        if ui.button(t.minimize_to_tray).clicked() {
-            self.minimize_to_tray(ui.ctx());
         }
     }
 
-    /// Render the wallpaper style selection and apply it immediately.
-    fn render_style_selector(
+    /// Render the stitch layout choice: automatic, a preset, or a saved layout.
+    fn render_layout_picker(
         &mut self,
         ui: &mut egui::Ui,
-        t: &Strings,
         settings_changed: &mut bool,
         restart_needed: &mut bool,
     ) {
-        let mut style_changed = false;
-        egui::ComboBox::from_label(t.style)
-            .selected_text(style_label(self.state.style, self.state.language))
+        let lang = self.state.language;
+        let automatic = loc(lang, "Automatic", "自動");
+        let selected = if self.state.stitch_layout.is_empty() {
+            automatic.to_string()
+        } else {
+            self.state.stitch_layout.clone()
+        };
+        let names: Vec<String> = self
+            .state
+            .stitch_layouts
+            .iter()
+            .chain(&StitchLayout::presets())
+            .map(|layout| layout.name.clone())
+            .collect();
+        let mut changed = false;
+        egui::ComboBox::from_id_source("stitch_layout_combo")
+            .selected_text(selected)
             .show_ui(ui, |ui| {
-                for mode in StyleMode::ALL {
-                    if ui
-                        .selectable_value(
-                            &mut self.state.style,
-                            mode,
-                            style_label(mode, self.state.language),
-                        )
-                        .changed()
-                    {
-                        style_changed = true;
-                    }
+                changed |= ui
+                    .selectable_value(&mut self.state.stitch_layout, String::new(), automatic)
+                    .changed();
+                for name in names {
+                    changed |= ui
+                        .selectable_value(&mut self.state.stitch_layout, name.clone(), name)
+                        .changed();
                 }
             });
-        if style_changed {
+        if changed {
             *settings_changed = true;
             *restart_needed = true;
-            if let Err(err) = set_wallpaper_style(self.state.style) {
-                self.status = err.to_string();
-            } else if !self.state.running {
-                // Reapply the cached wallpaper so the new style takes effect immediately.
-                if let Ok(cache_path) = cached_wallpaper_path() {
-                    if cache_path.exists() {
-                        let _ = set_wallpaper(&cache_path);
-                    }
-                }
-            }
         }
     }
 
-    /// Render action buttons (apply once, next, start/stop, reset).
-    fn render_controls(&mut self, ui: &mut egui::Ui, t: &Strings) {
-        let startup_once_mode =
-            self.settings.run_on_startup && self.settings.change_once_on_startup;
-        let primary_fill = if self.state.running {
-            Color32::from_rgb(186, 70, 70)
+    /// Render the editor that saves user-defined stitch layouts.
+    fn render_layout_editor(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_changed: &mut bool,
+        restart_needed: &mut bool,
+    ) {
+        let lang = self.state.language;
+        egui::CollapsingHeader::new(loc(lang, "Edit layouts", "編輯版面"))
+            .id_source("stitch_layout_editor")
+            .show(ui, |ui| {
+                ui.label(
+                    RichText::new(loc(
+                        lang,
+                        "First line `rows` or `columns`, then one line per row/column: optional `weight:` and slots P (portrait), L (landscape), A (any), each with an optional weight.",
+                        "第一行為 `rows` 或 `columns`，之後每行一列／欄：可選的 `權重:` 與格位 P（直向）、L（橫向）、A（不限），格位可加權重。",
+                    ))
+                    .small()
+                    .weak(),
+                );
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(loc(lang, "Name", "名稱"));
+                    ui.text_edit_singleline(&mut self.layout_name);
+                    if ui.button(loc(lang, "Copy current", "複製目前版面")).clicked() {
+                        let layout = self.state.active_layout();
+                        self.layout_spec = layout.spec();
+                        self.layout_name = layout.name;
+                    }
+                });
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.layout_spec)
+                        .code_editor()
+                        .desired_rows(4)
+                        .desired_width(f32::INFINITY),
+                );
+                ui.horizontal_wrapped(|ui| {
+                    if ui.button(loc(lang, "Save layout", "儲存版面")).clicked() {
+                        match StitchLayout::parse(&self.layout_name, &self.layout_spec) {
+                            Ok(layout) => {
+                                self.state.stitch_layout = layout.name.clone();
+                                match self
+                                    .state
+                                    .stitch_layouts
+                                    .iter_mut()
+                                    .find(|saved| saved.name == layout.name)
+                                {
+                                    Some(saved) => *saved = layout,
+                                    None => self.state.stitch_layouts.push(layout),
+                                }
+                                *settings_changed = true;
+                                *restart_needed = true;
+                            }
+                            Err(err) => self.status = err.to_string(),
+                        }
+                    }
+                    let saved = self
+                        .state
+                        .stitch_layouts
+                        .iter()
+                        .position(|layout| layout.name == self.layout_name);
+                    if let Some(idx) = saved
+                        && ui.button(loc(lang, "Delete layout", "刪除版面")).clicked()
+                    {
+                        let removed = self.state.stitch_layouts.remove(idx);
+                        if self.state.stitch_layout == removed.name {
+                            self.state.stitch_layout.clear();
+                        }
+                        *settings_changed = true;
+                        *restart_needed = true;
+                    }
+                });
+            });
+    }
+
+    /// Render the fixed/random interval choice and the random upper bound.
+    fn render_interval_mode(
+        &mut self,
+        ui: &mut egui::Ui,
+        enabled: bool,
+        settings_changed: &mut bool,
+        restart_needed: &mut bool,
+    ) {
+        let lang = self.state.language;
+        let mut changed = false;
+        ui.add_enabled_ui(enabled, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                egui::ComboBox::from_label(loc(lang, "Interval mode", "間隔模式"))
+                    .selected_text(interval_mode_label(self.state.interval_mode, lang))
+                    .show_ui(ui, |ui| {
+                        for mode in IntervalMode::ALL {
+                            changed |= ui
+                                .selectable_value(
+                                    &mut self.state.interval_mode,
+                                    mode,
+                                    interval_mode_label(mode, lang),
+                                )
+                                .changed();
+                        }
+                    });
+                if self.state.interval_mode == IntervalMode::Random {
+                    ui.label(loc(lang, "up to", "最長"));
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut self.state.interval_max_secs)
+                                .clamp_range(self.state.interval_secs..=86_400)
+                                .suffix(" s"),
+                        )
+                        .changed();
+                }
+            });
+        });
+        if changed {
+            *settings_changed = true;
+            *restart_needed = true;
+        }
+    }
+
+    /// Render the cron toggle, expression field, and next-change preview.
+    fn render_cron(
+        &mut self,
+        ui: &mut egui::Ui,
+        startup_once_mode: bool,
+        settings_changed: &mut bool,
+        restart_needed: &mut bool,
+    ) {
+        let lang = self.state.language;
+        ui.add_enabled_ui(!startup_once_mode, |ui| {
+            if ui
+                .checkbox(
+                    &mut self.state.cron_enabled,
+                    loc(lang, "Change on a cron schedule", "依 cron 排程更換"),
+                )
+                .changed()
+            {
+                *settings_changed = true;
+                *restart_needed = true;
+            }
+            if !self.state.cron_enabled {
+                return;
+            }
+
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.state.cron_expression)
+                    .hint_text("0 * * * *")
+                    .font(egui::TextStyle::Monospace),
+            );
+            // Half-typed expressions can parse; apply only once Enter or a click away
+            // finishes the edit.
+            let edited = response.lost_focus()
+                && self.state.cron_expression != self.settings.cron_expression;
+            match CronSchedule::parse(&self.state.cron_expression) {
+                Ok(cron) => {
+                    if edited {
+                        *settings_changed = true;
+                        *restart_needed = true;
+                    }
+                    let next = cron
+                        .next_after(library::unix_now())
+                        .and_then(|at| Local.timestamp_opt(at as i64, 0).single())
+                        .map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_else(|| "--".to_string());
+                    ui.label(format!(
+                        "{} {next}",
+                        loc(lang, "Next change:", "下次更換：")
+                    ));
+                }
+                Err(err) => {
+                    // Save the draft, but keep the running slideshow on its last valid timing.
+                    *settings_changed |= edited;
+                    ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+                }
+            }
+        });
+    }
+
+    /// Render the on-battery policy and its interval multiplier.
+    fn render_battery_policy(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_changed: &mut bool,
+        restart_needed: &mut bool,
+    ) {
+        let lang = self.state.language;
+        let mut changed = false;
+        ui.horizontal_wrapped(|ui| {
+            egui::ComboBox::from_label(loc(lang, "On battery", "使用電池時"))
+                .selected_text(battery_policy_label(self.state.battery_policy, lang))
+                .show_ui(ui, |ui| {
+                    for policy in BatteryPolicy::ALL {
+                        changed |= ui
+                            .selectable_value(
+                                &mut self.state.battery_policy,
+                                policy,
+                                battery_policy_label(policy, lang),
+                            )
+                            .changed();
+                    }
+                });
+            if self.state.battery_policy == BatteryPolicy::Lengthen {
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.state.battery_interval_factor)
+                            .clamp_range(2..=20)
+                            .prefix("×"),
+                    )
+                    .changed();
+            }
+        });
+        if changed {
+            *settings_changed = true;
+            *restart_needed = true;
+        }
+    }
+
+    /// Render the dry-run preview of upcoming changes.
+    fn render_preview(&mut self, ui: &mut egui::Ui) {
+        let lang = self.state.language;
+        ui.horizontal_wrapped(|ui| {
+            ui.label(loc(lang, "Next", "接下來"));
+            ui.add(
+                egui::DragValue::new(&mut self.preview_count)
+                    .clamp_range(1..=100)
+                    .suffix(loc(lang, " changes", " 次更換")),
+            );
+            if ui.button(loc(lang, "Simulate", "模擬")).clicked() {
+                match self.simulate_changes() {
+                    Ok(changes) => self.preview = changes,
+                    Err(err) => {
+                        self.preview.clear();
+                        self.status = err.to_string();
+                    }
+                }
+            }
+        });
+        if self.state.seed.is_none() && self.state.random_order {
+            ui.label(
+                RichText::new(loc(
+                    lang,
+                    "Random picks vary between runs unless a seed is set.",
+                    "未設定種子時，每次隨機結果不同。",
+                ))
+                .small()
+                .weak(),
+            );
+        }
+        if self.preview.is_empty() {
+            return;
+        }
+        egui::ScrollArea::vertical()
+            .id_source("preview_list")
+            .max_height(180.0)
+            .show(ui, |ui| {
+                for change in &self.preview {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(RichText::new(preview::format_time(change.at)).monospace());
+                        let names: Vec<String> = change
+                            .images
+                            .iter()
+                            .map(|path| display_name(path))
+                            .collect();
+                        ui.label(names.join(" + "));
+                    });
+                }
+            });
+    }
+
+    /// Render the list of applications that hold the wallpaper while running.
+    fn render_pause_apps(&mut self, ui: &mut egui::Ui, settings_changed: &mut bool) {
+        let lang = self.state.language;
+        ui.label(loc(
+            lang,
+            "Pause while these apps run (one per line)",
+            "以下程式執行時暫停（每行一個）",
+        ));
+        let edited = ui
+            .add(
+                egui::TextEdit::multiline(&mut self.pause_apps_text)
+                    .hint_text("vlc\ngame.exe")
+                    .desired_rows(2)
+                    .font(egui::TextStyle::Monospace),
+            )
+            .lost_focus();
+        if edited {
+            let apps: Vec<String> = self
+                .pause_apps_text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect();
+            // Blank lines and spacing edits don't change what the worker watches.
+            if apps != self.state.pause_apps {
+                self.state.pause_apps = apps;
+                *settings_changed = true;
+                // The running worker switches lists in place, keeping its countdown.
+                if let Some(worker) = &self.worker {
+                    worker.set_pause_apps(ProcessMonitor::new(
+                        &self.state.proc_root,
+                        &self.state.pause_apps,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Render the "don't repeat within" controls for random selection.
+    fn render_repeat_window(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_changed: &mut bool,
+        restart_needed: &mut bool,
+    ) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label(loc(
+                self.state.language,
+                "Don't repeat within",
+                "不重複範圍",
+            ));
+            let changes = ui.add(
+                egui::DragValue::new(&mut self.state.repeat_window_changes)
+                    .clamp_range(0..=10_000)
+                    .suffix(loc(self.state.language, " changes", " 次更換")),
+            );
+            let days = ui.add(
+                egui::DragValue::new(&mut self.state.repeat_window_days)
+                    .clamp_range(0..=365)
+                    .suffix(loc(self.state.language, " days", " 天")),
+            );
+            if changes.changed() || days.changed() {
+                *settings_changed = true;
+                *restart_needed = true;
+            }
+        });
+    }
+
+    /// Render the time-of-day rules that limit which folders are used.
+    fn render_schedule_section(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings_changed: &mut bool,
+        restart_needed: &mut bool,
+    ) {
+        let lang = self.state.language;
+        let mut changed = ui
+            .checkbox(
+                &mut self.state.schedule_enabled,
+                loc(lang, "Use folders by time of day", "依時段使用資料夾"),
+            )
+            .changed();
+
+        if self.state.schedule_enabled {
+            let folders: Vec<PathBuf> = self
+                .state
+                .folders
+                .iter()
+                .map(|folder| folder.path.clone())
+                .collect();
+            let mut remove_idx = None;
+            for (idx, rule) in self.state.schedule_rules.iter_mut().enumerate() {
+                ui.push_id(("schedule_rule", idx), |ui| {
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            let name = ui.add(
+                                egui::TextEdit::singleline(&mut rule.name)
+                                    .hint_text(loc(lang, "Rule name", "規則名稱"))
+                                    .desired_width(140.0),
+                            );
+                            changed |= edit_finished(&name);
+                            if ui.small_button(loc(lang, "Remove", "移除")).clicked() {
+                                remove_idx = Some(idx);
+                            }
+                        });
+                        ui.horizontal_wrapped(|ui| {
+                            for (day, label) in rule.weekdays.iter_mut().zip(weekday_labels(lang)) {
+                                changed |= ui.toggle_value(day, label).changed();
+                            }
+                        });
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label(loc(lang, "From", "從"));
+                            changed |= boundary_editor(
+                                ui,
+                                "start",
+                                &mut rule.start_anchor,
+                                &mut rule.start_minute,
+                                lang,
+                            );
+                        });
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label(loc(lang, "to", "到"));
+                            changed |= boundary_editor(
+                                ui,
+                                "end",
+                                &mut rule.end_anchor,
+                                &mut rule.end_minute,
+                                lang,
+                            );
+                        });
+                        if folders.is_empty() {
+                            ui.label(loc(lang, "Add folders to use them here", "請先新增資料夾"));
+                        }
+                        for path in &folders {
+                            let key = path.to_string_lossy().to_string();
+                            let mut used = rule.folders.contains(&key);
+                            if ui.checkbox(&mut used, display_name(path)).changed() {
+                                if used {
+                                    rule.folders.push(key);
+                                } else {
+                                    rule.folders.retain(|folder| folder != &key);
+                                }
+                                changed = true;
+                            }
+                        }
+                    });
+                });
+            }
+            if let Some(idx) = remove_idx {
+                self.state.schedule_rules.remove(idx);
+                changed = true;
+            }
+            if ui.button(loc(lang, "Add rule", "新增規則")).clicked() {
+                self.state.schedule_rules.push(ScheduleRule::default());
+                changed = true;
+            }
+
+            let uses_sun = self.state.schedule_rules.iter().any(|rule| {
+                rule.start_anchor != TimeAnchor::Clock || rule.end_anchor != TimeAnchor::Clock
+            });
+            if uses_sun {
+                changed |= self.render_location(ui);
+            }
+        }
+
+        // Finishing an edit that ended where it started leaves the worker alone.
+        let edited = self.state.schedule_enabled != self.settings.schedule_enabled
+            || self.state.schedule_rules != self.settings.schedule_rules
+            || self.state.location != self.settings.location;
+        if changed && edited {
+            *settings_changed = true;
+            *restart_needed = true;
+        }
+    }
+
+    /// Render the latitude/longitude used for sun anchors, with today's sun times.
+    ///
+    /// Returns true when an edit was finished.
+    fn render_location(&mut self, ui: &mut egui::Ui) -> bool {
+        let lang = self.state.language;
+        let mut changed = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.label(loc(lang, "Location", "位置"));
+            // The sun times below follow the drag; the worker only gets the result.
+            let latitude = ui.add(
+                egui::DragValue::new(&mut self.state.location.latitude)
+                    .clamp_range(-90.0..=90.0)
+                    .speed(0.1)
+                    .fixed_decimals(2)
+                    .prefix(loc(lang, "lat ", "緯度 ")),
+            );
+            let longitude = ui.add(
+                egui::DragValue::new(&mut self.state.location.longitude)
+                    .clamp_range(-180.0..=180.0)
+                    .speed(0.1)
+                    .fixed_decimals(2)
+                    .prefix(loc(lang, "lon ", "經度 ")),
+            );
+            changed = edit_finished(&latitude) || edit_finished(&longitude);
+        });
+
+        let today = Local::now().date_naive();
+        let sun_time = |anchor| {
+            schedule::anchor_minute(anchor, self.state.location, today)
+                .map(schedule::format_minute)
+                .unwrap_or_else(|| "--:--".to_string())
+        };
+        ui.label(format!(
+            "{} {} · {} {}",
+            loc(lang, "Today: sunrise", "今日：日出"),
+            sun_time(TimeAnchor::Sunrise),
+            loc(lang, "sunset", "日落"),
+            sun_time(TimeAnchor::Sunset),
+        ));
+        changed
+    }
+
+    /// Render startup-related options (run on startup, minimize to tray).
+    fn render_startup_section(&mut self, ui: &mut egui::Ui, t: &Strings) {
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut self.settings.run_on_startup, t.run_on_startup)
+                .changed()
+            {
+                let result = if self.settings.run_on_startup {
+                    startup::enable()
+                } else {
+                    startup::disable()
+                };
+                if let Err(err) = result {
+                    self.status = err.to_string();
+                    self.settings.run_on_startup = !self.settings.run_on_startup;
+                } else {
+                    if !self.settings.run_on_startup {
+                        self.settings.change_once_on_startup = false;
+                    }
+                    if let Err(err) = settings::save(&self.settings) {
+                        self.status = err.to_string();
+                    }
+                }
+            }
+            if self.settings.run_on_startup
+                && ui
+                    .checkbox(
+                        &mut self.settings.change_once_on_startup,
+                        t.change_once_on_startup,
+                    )
+                    .changed()
+            {
+                if self.settings.change_once_on_startup {
+                    self.state.running = false;
+                    self.settings.running = false;
+                    self.stop_worker();
+                }
+                if let Err(err) = settings::save(&self.settings) {
+                    self.status = err.to_string();
+                }
+            }
+            if ui
+                .checkbox(
+                    &mut self.settings.minimize_to_tray_on_start,
+                    t.minimize_to_tray_on_start,
+                )
+                .changed()
+            {
+                if let Err(err) = settings::save(&self.settings) {
+                    self.status = err.to_string();
+                }
+            }
+        });
+        if ui.button(t.minimize_to_tray).clicked() {
+            self.minimize_to_tray(ui.ctx());
+        }
+    }
+
+    /// Render the wallpaper style selection and apply it immediately.
+    fn render_style_selector(
+        &mut self,
+        ui: &mut egui::Ui,
+        t: &Strings,
+        settings_changed: &mut bool,
+        restart_needed: &mut bool,
+    ) {
+        let mut style_changed = false;
+        egui::ComboBox::from_label(t.style)
+            .selected_text(style_label(self.state.style, self.state.language))
+            .show_ui(ui, |ui| {
+                for mode in StyleMode::ALL {
+                    if ui
+                        .selectable_value(
+                            &mut self.state.style,
+                            mode,
+                            style_label(mode, self.state.language),
+                        )
+                        .changed()
+                    {
+                        style_changed = true;
+                    }
+                }
+            });
+
+        let lang = self.state.language;
+        style_changed |= ui
+            .checkbox(
+                &mut self.state.prerender,
+                loc(lang, "Render at screen size", "依螢幕尺寸預先繪製"),
+            )
+            .changed();
+        if self.state.prerender {
+            ui.horizontal_wrapped(|ui| {
+                // 0 means "detect", shown as "auto".
+                let auto = |value: f64, _: std::ops::RangeInclusive<usize>| {
+                    if value == 0.0 {
+                        "auto".to_string()
+                    } else {
+                        format!("{value}")
+                    }
+                };
+                let width = ui.add(
+                    egui::DragValue::new(&mut self.state.render_width)
+                        .clamp_range(0..=16_384)
+                        .custom_formatter(auto),
+                );
+                ui.label("×");
+                let height = ui.add(
+                    egui::DragValue::new(&mut self.state.render_height)
+                        .clamp_range(0..=16_384)
+                        .custom_formatter(auto),
+                );
+                // Re-render once the size is settled, not on every drag frame.
+                style_changed |= (edit_finished(&width) || edit_finished(&height))
+                    && (self.state.render_width, self.state.render_height)
+                        != (self.settings.render_width, self.settings.render_height);
+                egui::ComboBox::from_id_source("resample_filter")
+                    .selected_text(resample_filter_label(self.state.resample_filter, lang))
+                    .show_ui(ui, |ui| {
+                        for filter in ResampleFilter::ALL {
+                            style_changed |= ui
+                                .selectable_value(
+                                    &mut self.state.resample_filter,
+                                    filter,
+                                    resample_filter_label(filter, lang),
+                                )
+                                .changed();
+                        }
+                    });
+            });
+            match self.state.render_target() {
+                Some(target) => ui.label(
+                    RichText::new(format!(
+                        "{} {}×{}",
+                        loc(lang, "Output:", "輸出："),
+                        target.width,
+                        target.height
+                    ))
+                    .small()
+                    .weak(),
+                ),
+                None => ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    loc(
+                        lang,
+                        "Screen size unknown; set it above.",
+                        "無法偵測螢幕尺寸，請於上方設定。",
+                    ),
+                ),
+            };
+        }
+
+        // Stitched cells use the same fill, so it is offered with or without pre-rendering.
+        ui.horizontal_wrapped(|ui| {
+            ui.label(loc(lang, "Letterbox:", "留邊填充："));
+            egui::ComboBox::from_id_source("letterbox_fill")
+                .selected_text(letterbox_fill_label(self.state.letterbox_fill, lang))
+                .show_ui(ui, |ui| {
+                    for fill in LetterboxFill::ALL {
+                        style_changed |= ui
+                            .selectable_value(
+                                &mut self.state.letterbox_fill,
+                                fill,
+                                letterbox_fill_label(fill, lang),
+                            )
+                            .changed();
+                    }
+                });
+            if self.state.letterbox_fill == LetterboxFill::Solid {
+                style_changed |= ui
+                    .color_edit_button_srgb(&mut self.state.letterbox_color)
+                    .changed();
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label(loc(lang, "Crop:", "裁切："));
+            for mode in CropMode::ALL {
+                style_changed |= ui
+                    .selectable_value(&mut self.state.crop_mode, mode, crop_mode_label(mode, lang))
+                    .changed();
+            }
+        });
+        if self.state.style == StyleMode::Span {
+            style_changed |= self.render_span_displays(ui, lang);
+        }
+
+        if style_changed {
+            *settings_changed = true;
+            *restart_needed = true;
+            if let Err(err) = set_wallpaper_style(self.state.os_style()) {
+                self.status = err.to_string();
+            } else if !self.state.running {
+                self.refresh_current_wallpaper();
+            }
+        }
+    }
+
+    /// Render the per-display Span toggle and the monitor layout it composes for.
+    ///
+    /// Returns true when anything changed.
+    fn render_span_displays(&mut self, ui: &mut egui::Ui, lang: Language) -> bool {
+        let mut changed = ui
+            .checkbox(
+                &mut self.state.span_per_display,
+                loc(lang, "One image per display", "每個螢幕各一張"),
+            )
+            .changed();
+        if !self.state.span_per_display {
+            return changed;
+        }
+
+        let mut remove = None;
+        let mut edited = false;
+        egui::Grid::new("span_displays_grid")
+            .num_columns(4)
+            .spacing(egui::vec2(6.0, 4.0))
+            .show(ui, |ui| {
+                ui.label(loc(lang, "Position", "位置"));
+                ui.label(loc(lang, "Resolution", "解析度"));
+                ui.label(loc(lang, "Rotation", "旋轉"));
+                ui.end_row();
+                for (idx, display) in self.state.span_displays.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        edited |= edit_finished(&ui.add(egui::DragValue::new(&mut display.x)));
+                        ui.label(",");
+                        edited |= edit_finished(&ui.add(egui::DragValue::new(&mut display.y)));
+                    });
+                    ui.horizontal(|ui| {
+                        edited |=
+                            edit_finished(&ui.add(
+                                egui::DragValue::new(&mut display.width).clamp_range(1..=16_384),
+                            ));
+                        ui.label("×");
+                        edited |= edit_finished(&ui.add(
+                            egui::DragValue::new(&mut display.height).clamp_range(1..=16_384),
+                        ));
+                    });
+                    egui::ComboBox::from_id_source(("span_rotation", idx))
+                        .selected_text(display_rotation_label(display.rotation, lang))
+                        .show_ui(ui, |ui| {
+                            for rotation in DisplayRotation::ALL {
+                                changed |= ui
+                                    .selectable_value(
+                                        &mut display.rotation,
+                                        rotation,
+                                        display_rotation_label(rotation, lang),
+                                    )
+                                    .changed();
+                            }
+                        });
+                    if ui.small_button("✕").clicked() {
+                        remove = Some(idx);
+                    }
+                    ui.end_row();
+                }
+            });
+        // Positions and sizes apply once an edit ends, not on every drag frame.
+        changed |= edited && self.state.span_displays != self.settings.span_displays;
+        if let Some(idx) = remove {
+            self.state.span_displays.remove(idx);
+            changed = true;
+        }
+        if ui.button(loc(lang, "Add display", "新增螢幕")).clicked() {
+            // New displays go to the right of the others, matching the last one.
+            let template = self.state.span_displays.last().copied().unwrap_or_default();
+            let x = span_bounds(&self.state.span_displays)
+                .map_or(0, |(left, _, width, _)| left.saturating_add_unsigned(width));
+            self.state.span_displays.push(SpanDisplay {
+                x,
+                y: template.y,
+                ..template
+            });
+            changed = true;
+        }
+
+        match span_bounds(&self.state.span_displays) {
+            Some((_, _, width, height)) => {
+                ui.label(
+                    RichText::new(format!(
+                        "{} {width}×{height}",
+                        loc(lang, "Canvas:", "畫布：")
+                    ))
+                    .small()
+                    .weak(),
+                );
+                if screen_size(StyleMode::Span).is_some_and(|size| size != (width, height)) {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        loc(
+                            lang,
+                            "Layout differs from the desktop size; Windows will rescale it.",
+                            "版面與桌面尺寸不同，Windows 會重新縮放。",
+                        ),
+                    );
+                }
+            }
+            None => {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    loc(
+                        lang,
+                        "Add your displays to compose the span.",
+                        "請新增螢幕以組合跨螢幕桌布。",
+                    ),
+                );
+            }
+        }
+        changed
+    }
+
+    /// Show the current wallpaper again under the new style settings, rendering it
+    /// in the background.
+    fn refresh_current_wallpaper(&mut self) {
+        // A pre-rendered frame or stitched canvas bakes the style in, so rebuild it
+        // from the sources.
+        if ((self.state.prerender || self.state.span_layout().is_some())
+            && !self.current_images.is_empty())
+            || self.current_images.len() > 1
+        {
+            self.refresher.request(Refresh::Render(
+                Box::new(self.slideshow_config()),
+                self.current_images.clone(),
+            ));
+            return;
+        }
+        // Reapply the cached wallpaper so the new style takes effect immediately.
+        self.refresher.request(Refresh::Reapply);
+    }
+
+    /// Render action buttons (apply once, next, start/stop, reset).
+    fn render_controls(&mut self, ui: &mut egui::Ui, t: &Strings) {
+        let startup_once_mode =
+            self.settings.run_on_startup && self.settings.change_once_on_startup;
+        let primary_fill = if self.state.running {
+            Color32::from_rgb(186, 70, 70)
         } else {
             Color32::from_rgb(61, 140, 100)
         };
@@ -812,6 +1958,26 @@ impl WallpaperApp {
                 self.request_next();
             }
 
+            let lang = self.state.language;
+            ui.menu_button(loc(lang, "Keep wallpaper", "保留桌布"), |ui| {
+                for (request, label) in pin_choices(lang) {
+                    if ui.button(label).clicked() {
+                        self.set_pin(Some(request.resolve(library::unix_now())));
+                        ui.close_menu();
+                    }
+                }
+                if self.pin.is_some() {
+                    ui.separator();
+                    if ui
+                        .button(loc(lang, "Resume rotation", "恢復輪播"))
+                        .clicked()
+                    {
+                        self.set_pin(None);
+                        ui.close_menu();
+                    }
+                }
+            });
+
             if ui
                 .add_enabled(
                     self.state.running || !startup_once_mode,
@@ -841,6 +2007,137 @@ impl WallpaperApp {
                 self.reset_to_defaults(ui.ctx());
             }
         });
+
+        self.render_rating(ui);
+    }
+
+    /// Render star ratings for the images on the current wallpaper.
+    fn render_rating(&mut self, ui: &mut egui::Ui) {
+        if self.current_images.is_empty() {
+            return;
+        }
+        let mut changed = false;
+        let mut open_focus = None;
+        {
+            let mut library = library::lock(&self.library);
+            for path in &self.current_images {
+                let rating = library.rating(path);
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(loc(self.state.language, "Rating", "評分"));
+                    for star in 1..=MAX_RATING {
+                        let color = if star <= rating {
+                            Color32::from_rgb(230, 180, 60)
+                        } else {
+                            ui.visuals().weak_text_color()
+                        };
+                        let label = RichText::new("★").color(color);
+                        if ui.add(Button::new(label).frame(false)).clicked() {
+                            // Clicking the current rating again clears it.
+                            let new_rating = if star == rating { 0 } else { star };
+                            library.set_rating(path, new_rating);
+                            changed = true;
+                        }
+                    }
+                    let has_focus = self.focus.point(path).is_some();
+                    let marker = if has_focus { "◉" } else { "◎" };
+                    if ui
+                        .add(Button::new(marker).frame(false))
+                        .on_hover_text(loc(self.state.language, "Focal point", "焦點"))
+                        .clicked()
+                    {
+                        open_focus = Some(path.clone());
+                    }
+                    ui.label(RichText::new(display_name(path)).small().weak());
+                });
+            }
+        }
+        if changed && let Err(err) = library::save(&library::lock(&self.library)) {
+            self.status = err.to_string();
+        }
+        if let Some(path) = open_focus {
+            self.open_focus_editor(ui.ctx(), path);
+        }
+        self.render_focus_editor(ui);
+    }
+
+    /// Load a preview of `path` for picking its focal point.
+    fn open_focus_editor(&mut self, ctx: &egui::Context, path: PathBuf) {
+        match image_ops::load_thumbnail(&path, FOCUS_PREVIEW_SIZE) {
+            Ok(thumb) => {
+                let size = [thumb.width() as usize, thumb.height() as usize];
+                let image = egui::ColorImage::from_rgb(size, thumb.as_raw());
+                let texture = ctx.load_texture("focus_preview", image, Default::default());
+                self.focus_editor = Some(FocusEditor { path, texture });
+            }
+            Err(err) => self.status = err.to_string(),
+        }
+    }
+
+    /// Render the focal point picker: clicking the preview sets the point.
+    fn render_focus_editor(&mut self, ui: &mut egui::Ui) {
+        let Some(editor) = &self.focus_editor else {
+            return;
+        };
+        let lang = self.state.language;
+        let path = editor.path.clone();
+        let texture_size = editor.texture.size_vec2();
+        let scale = (ui.available_width() / texture_size.x).min(1.0);
+        let response = ui.add(
+            egui::Image::new(&editor.texture)
+                .fit_to_exact_size(texture_size * scale)
+                .sense(egui::Sense::click()),
+        );
+        let rect = response.rect;
+
+        let mut point = self.focus.point(&path);
+        let mut changed = false;
+        if let Some(pos) = response
+            .interact_pointer_pos()
+            .filter(|_| response.clicked())
+        {
+            point = Some(FocusPoint::new(
+                (pos.x - rect.left()) / rect.width(),
+                (pos.y - rect.top()) / rect.height(),
+            ));
+            changed = true;
+        }
+        if let Some(point) = point {
+            let center = rect.left_top() + egui::vec2(point.x, point.y) * rect.size();
+            let painter = ui.painter();
+            painter.circle_stroke(center, 7.0, Stroke::new(3.0, Color32::BLACK));
+            painter.circle_stroke(center, 7.0, Stroke::new(1.5, Color32::WHITE));
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label(
+                RichText::new(loc(
+                    lang,
+                    "Click the part that crops must keep.",
+                    "點選裁切時必須保留的部分。",
+                ))
+                .small()
+                .weak(),
+            );
+            if point.is_some() && ui.button(loc(lang, "Clear", "清除")).clicked() {
+                point = None;
+                changed = true;
+            }
+            if ui.button(loc(lang, "Done", "完成")).clicked() {
+                self.focus_editor = None;
+            }
+        });
+
+        if changed {
+            let result = self
+                .focus
+                .set(&path, point)
+                .and_then(|_| focus::save(&self.focus));
+            match result {
+                // Crops made by the app change; the desktop shows the new one right away.
+                Ok(()) => self.refresh_current_wallpaper(),
+                Err(err) => self.status = err.to_string(),
+            }
+        }
     }
 
     /// Reset all settings to defaults.
@@ -865,42 +2162,43 @@ impl WallpaperApp {
     /// Apply a single wallpaper immediately, without starting the slideshow.
     fn apply_once(&mut self) -> Result<()> {
         self.ensure_image_index()?;
-        let images = self.indexed_images.clone();
-        if images.is_empty() {
+        if self.indexed_images.is_empty() {
             let t = strings(self.state.language);
             return Err(anyhow::anyhow!(t.no_images));
         }
-        set_wallpaper_style(self.state.style)?;
-
-        let processed = if self.state.stitch_enabled {
-            let count = (self.state.stitch_count as usize).min(images.len());
-            let mut selected = Vec::with_capacity(count);
-            let mut last: Option<std::path::PathBuf> = None;
-            for _ in 0..count {
-                let choice = pick_random(&images, last.as_ref())?;
-                last = Some(choice.clone());
-                selected.push(choice);
-            }
-            stitch_images(
-                &selected,
-                self.state.auto_rotate,
-                self.state.stitch_orientation,
-                true, // always crop
-                self.state.stitch_crop_width,
-                self.state.stitch_crop_height,
-            )?
-        } else {
-            let choice = pick_random(&images, None)?;
-            process_image(&choice, self.state.auto_rotate)?
-        };
+        // One-off changes are always random, but still honor the random mode.
+        let mut config = self.slideshow_config();
+        config.random_order = true;
+        set_wallpaper_style(config.style)?;
+        let mut selector = config.selector();
+        // A fixed seed would make every click pick the same image, so always use entropy.
+        let mut rng = make_rng(None);
+        let selected = selector.pick_batch(
+            config.batch_size(),
+            &mut rng,
+            &library::lock(&self.library),
+            library::unix_now(),
+        )?;
 
+        let processed = render_images(&config, &selected)?;
         set_wallpaper(&processed)?;
+        // Replacing the wallpaper by hand ends any pin on the old one.
+        if self.pin.is_some() {
+            self.set_pin(None);
+        }
+        let _ = library::record_change(&self.library, &selected);
+        let now = library::unix_now();
+        let _ = session::record(&selected, self.next_change_at, now);
+        self.current_images = selected;
         Ok(())
     }
 
     /// Advance the slideshow or apply a single image when idle.
     fn request_next(&mut self) {
         if let Some(worker) = &self.worker {
+            // The worker drops its own pin on Next; keep the UI and disk in step.
+            self.pin = None;
+            let _ = session::set_pin(None);
             worker.request_next();
         } else {
             match self.apply_once() {
@@ -913,38 +2211,103 @@ impl WallpaperApp {
         }
     }
 
+    /// Hold or release the current wallpaper in the worker and on disk.
+    fn set_pin(&mut self, pin: Option<Pin>) {
+        self.pin = pin;
+        if let Some(worker) = &self.worker {
+            worker.set_pin(pin);
+        }
+        let _ = session::set_pin(pin);
+    }
+
+    /// Status-bar text for the pin or the next planned change.
+    fn timing_text(&self) -> Option<String> {
+        let lang = self.state.language;
+        let format_time = |at: u64| {
+            Local
+                .timestamp_opt(at as i64, 0)
+                .single()
+                .map(|time| time.format("%H:%M:%S").to_string())
+        };
+        if let Some(reason) = &self.paused {
+            return Some(pause_label(reason, lang));
+        }
+        match self.pin.filter(|pin| pin.is_active(library::unix_now())) {
+            Some(Pin::Indefinitely) => Some(loc(lang, "Kept", "已保留").to_string()),
+            Some(Pin::Until(at)) => Some(format!(
+                "{} {}",
+                loc(lang, "Kept until", "保留至"),
+                format_time(at)?
+            )),
+            None => Some(format!(
+                "{} {}",
+                loc(lang, "Next change", "下次更換"),
+                format_time(self.next_change_at?)?
+            )),
+        }
+    }
+
     /// Start (or restart) the slideshow worker.
     fn start_slideshow(&mut self) -> Result<()> {
+        // A restart for settings other than timing keeps the planned change time.
+        let timing = self.state.change_timing();
+        let carried = self
+            .next_change_at
+            .filter(|_| self.worker.is_some() && self.worker_timing.as_ref() == Some(&timing));
         self.stop_worker();
         self.ensure_image_index()?;
         if self.indexed_images.is_empty() {
             let t = strings(self.state.language);
             return Err(anyhow::anyhow!(t.no_images));
         }
-        let worker = SlideshowWorker::start(
-            self.indexed_images.clone(),
-            self.state.auto_rotate,
-            self.state.style,
-            Duration::from_secs(self.state.interval_secs),
-            self.state.random_order,
-            self.state.stitch_enabled,
-            self.state.stitch_count,
-            self.state.stitch_orientation,
-            self.state.stitch_crop_width,
-            self.state.stitch_crop_height,
-        )?;
+        let cron = self.state.cron_schedule()?;
+        let worker = SlideshowWorker::start(SlideshowConfig {
+            cron,
+            resume_at: self.pending_resume.take().or(carried),
+            ..self.slideshow_config()
+        })?;
 
         self.worker = Some(worker);
+        self.worker_timing = Some(timing);
         self.state.running = true;
         Ok(())
     }
 
+    /// Simulate upcoming changes with the current settings, without touching the desktop.
+    fn simulate_changes(&mut self) -> Result<Vec<PlannedChange>> {
+        self.ensure_image_index()?;
+        if self.indexed_images.is_empty() {
+            let t = strings(self.state.language);
+            return Err(anyhow::anyhow!(t.no_images));
+        }
+        let mut config = self.slideshow_config();
+        config.cron = self.state.cron_schedule()?;
+        // A running slideshow continues from its planned change; a stopped one starts now.
+        config.resume_at = self.next_change_at.filter(|_| self.worker.is_some());
+        preview::simulate(&config, self.preview_count, library::unix_now())
+    }
+
+    /// Snapshot the runtime state into a worker configuration.
+    ///
+    /// Timing is left to the interval; [`Self::start_slideshow`] adds the cron schedule
+    /// and any deadline resumed from the previous run.
+    fn slideshow_config(&self) -> SlideshowConfig {
+        self.state.slideshow_config(
+            self.indexed_images.clone(),
+            self.current_images.clone(),
+            self.pin,
+            Arc::clone(&self.library),
+        )
+    }
+
     /// Stop the slideshow worker without blocking the UI thread.
     fn stop_worker(&mut self) {
         if let Some(worker) = self.worker.take() {
             worker.stop();
         }
         self.state.running = false;
+        self.next_change_at = None;
+        self.paused = None;
     }
 
     /// Restart the slideshow if it is currently running.
@@ -985,7 +2348,8 @@ impl WallpaperApp {
             return Ok(());
         }
 
-        let images = collect_images(&self.state.folders, self.state.single_image.as_deref())?;
+        let mut images = collect_images(&self.state.folders, self.state.single_image.as_deref())?;
+        sort_images(&mut images, &self.state.folders, self.state.sort_mode);
         self.folder_image_counts = self
             .state
             .folders
@@ -993,7 +2357,7 @@ impl WallpaperApp {
             .map(|folder| {
                 images
                     .iter()
-                    .filter(|path| source_contains(folder, path.as_path()))
+                    .filter(|path| folder.contains(path.as_path()))
                     .count()
             })
             .collect();
@@ -1004,6 +2368,9 @@ impl WallpaperApp {
 
     /// Drain background worker events into UI state.
     fn drain_events(&mut self) {
+        if let Some(err) = self.refresher.take_failure() {
+            self.status = err;
+        }
         let mut events = Vec::new();
         if let Some(worker) = &self.worker {
             worker.drain_events(&mut events);
@@ -1011,6 +2378,9 @@ impl WallpaperApp {
         for evt in events {
             match evt {
                 SlideshowEvent::Info(msg) => self.status = msg,
+                SlideshowEvent::Applied(paths) => self.current_images = paths,
+                SlideshowEvent::Scheduled(at) => self.next_change_at = Some(at),
+                SlideshowEvent::Paused(reason) => self.paused = reason,
                 SlideshowEvent::Error(msg) => {
                     self.status = msg;
                     self.state.running = false;
@@ -1020,6 +2390,19 @@ impl WallpaperApp {
         }
     }
 
+    /// Run commands received from the tray menu and the IPC listener.
+    fn handle_remote_commands(&mut self) {
+        while let Ok(command) = self.remote_rx.try_recv() {
+            match command {
+                RemoteCommand::Next => self.request_next(),
+                RemoteCommand::Pin(request) => {
+                    self.set_pin(Some(request.resolve(library::unix_now())))
+                }
+                RemoteCommand::Unpin => self.set_pin(None),
+            }
+        }
+    }
+
     /// Handle minimize and restore events from the tray icon.
     fn handle_tray_events(&mut self, ctx: &egui::Context) {
         // Apply deferred opacity once the window is fully ready (after 2 frames).
@@ -1159,6 +2542,9 @@ fn create_tray_icon(language: Language) -> Option<TrayIcon> {
     let tray_icon = TrayIconBuilder::new()
         .with_tooltip(t.title)
         .with_icon(icon)
+        .with_menu(Box::new(create_tray_menu(language)))
+        // Left click restores the window; the menu stays on right click.
+        .with_menu_on_left_click(false)
         .build();
 
     tray_icon.ok().map(|tray| {
@@ -1167,6 +2553,40 @@ fn create_tray_icon(language: Language) -> Option<TrayIcon> {
     })
 }
 
+/// Build the tray context menu; item ids map back through [`tray_menu_command`].
+fn create_tray_menu(language: Language) -> Menu {
+    let t = strings(language);
+    let menu = Menu::new();
+    let _ = menu.append(&MenuItem::with_id("next", t.next_image, true, None));
+    let _ = menu.append(&PredefinedMenuItem::separator());
+    for (request, label) in pin_choices(language) {
+        let _ = menu.append(&MenuItem::with_id(
+            format!("pin {}", request.to_arg()).as_str(),
+            label,
+            true,
+            None,
+        ));
+    }
+    let _ = menu.append(&MenuItem::with_id(
+        "unpin",
+        loc(language, "Resume rotation", "恢復輪播"),
+        true,
+        None,
+    ));
+    menu
+}
+
+/// Map a tray menu item id to the command it triggers.
+fn tray_menu_command(id: &str) -> Option<RemoteCommand> {
+    RemoteCommand::parse(id).ok()
+}
+
+/// Queue a command for the UI thread and wake it up.
+fn forward_remote(tx: &Sender<RemoteCommand>, ctx: &egui::Context, command: RemoteCommand) {
+    let _ = tx.send(command);
+    ctx.request_repaint();
+}
+
 /// Build a simple fallback tray icon (white border, blue fill).
 fn default_tray_icon() -> Option<Icon> {
     let size = 16u32;
@@ -1198,6 +2618,234 @@ fn style_label(mode: StyleMode, lang: Language) -> &'static str {
     }
 }
 
+/// Map a sort mode to its localized label.
+fn sort_label(mode: SortMode, lang: Language) -> &'static str {
+    match mode {
+        SortMode::Name => loc(lang, "Name", "名稱"),
+        SortMode::NaturalName => loc(lang, "Natural name", "自然名稱"),
+        SortMode::Modified => loc(lang, "Date modified", "修改日期"),
+        SortMode::ExifDate => loc(lang, "Date taken (EXIF)", "拍攝日期 (EXIF)"),
+        SortMode::Size => loc(lang, "File size", "檔案大小"),
+        SortMode::FolderRoundRobin => loc(lang, "Folder round-robin", "資料夾輪流"),
+    }
+}
+
+/// Map a random mode to its localized label.
+fn random_mode_label(mode: RandomMode, lang: Language) -> &'static str {
+    match mode {
+        RandomMode::Uniform => loc(lang, "Any image", "所有圖片平均"),
+        RandomMode::ByFolder => loc(lang, "Folder first (weighted)", "先選資料夾（權重）"),
+        RandomMode::Weighted => loc(lang, "Ratings and show counts", "依評分與播放次數"),
+        RandomMode::Daily => loc(lang, "Wallpaper of the day", "每日桌布"),
+    }
+}
+
+/// Map a schedule anchor to its localized label.
+fn anchor_label(anchor: TimeAnchor, lang: Language) -> &'static str {
+    match anchor {
+        TimeAnchor::Clock => loc(lang, "Clock time", "固定時間"),
+        TimeAnchor::CivilDawn => loc(lang, "Civil dawn", "民用晨光"),
+        TimeAnchor::Sunrise => loc(lang, "Sunrise", "日出"),
+        TimeAnchor::Sunset => loc(lang, "Sunset", "日落"),
+        TimeAnchor::CivilDusk => loc(lang, "Civil dusk", "民用暮光"),
+    }
+}
+
+/// Map a battery policy to its localized label.
+fn battery_policy_label(policy: BatteryPolicy, lang: Language) -> &'static str {
+    match policy {
+        BatteryPolicy::Ignore => loc(lang, "Keep interval", "維持間隔"),
+        BatteryPolicy::Lengthen => loc(lang, "Lengthen interval", "延長間隔"),
+        BatteryPolicy::Pause => loc(lang, "Pause", "暫停"),
+    }
+}
+
+/// Render `images` into the cached wallpaper with the config's rotation, stitch,
+/// and pre-render settings; more than one image is stitched or made a collage,
+/// unless each display of a Span wallpaper gets its own.
+fn render_images(config: &SlideshowConfig, images: &[PathBuf]) -> Result<PathBuf> {
+    if let Some(displays) = &config.span_displays {
+        return span_images(images, displays, &config.span_options());
+    }
+    match images {
+        [] => Err(anyhow::anyhow!("no images to render")),
+        [single] => process_image(single, config.auto_rotate, config.render),
+        _ if config.collage.is_some() => {
+            collage_images(images, &config.stitch_options(), config.render)
+        }
+        _ => stitch_images(
+            images,
+            config.auto_rotate,
+            &config.stitch_options(),
+            config.render,
+        ),
+    }
+}
+
+/// Localized label for a display rotation.
+fn display_rotation_label(rotation: DisplayRotation, lang: Language) -> &'static str {
+    match rotation {
+        DisplayRotation::Landscape => loc(lang, "Landscape", "橫向"),
+        DisplayRotation::Portrait => loc(lang, "Portrait", "直向"),
+        DisplayRotation::LandscapeFlipped => loc(lang, "Landscape (flipped)", "橫向（翻轉）"),
+        DisplayRotation::PortraitFlipped => loc(lang, "Portrait (flipped)", "直向（翻轉）"),
+    }
+}
+
+/// Localized label for a stitch scaling mode.
+fn stitch_scaling_label(scaling: StitchScaling, lang: Language) -> &'static str {
+    match scaling {
+        StitchScaling::Native => loc(lang, "Native size", "原始尺寸"),
+        StitchScaling::Normalized => loc(lang, "Common row height", "統一列高"),
+        StitchScaling::Cover => loc(lang, "Crop to cells", "裁切填滿格位"),
+    }
+}
+
+/// Localized label for a crop mode.
+fn crop_mode_label(mode: CropMode, lang: Language) -> &'static str {
+    match mode {
+        CropMode::Center => loc(lang, "Centre", "置中"),
+        CropMode::Smart => loc(lang, "Smart (keep detail)", "智慧（保留細節）"),
+    }
+}
+
+/// Localized label for a letterbox fill.
+fn letterbox_fill_label(fill: LetterboxFill, lang: Language) -> &'static str {
+    match fill {
+        LetterboxFill::Solid => loc(lang, "Solid colour", "純色"),
+        LetterboxFill::Blur => loc(lang, "Blurred image", "模糊影像"),
+        LetterboxFill::Dominant => loc(lang, "Dominant colour", "主色調"),
+        LetterboxFill::Mirror => loc(lang, "Mirrored edges", "鏡射邊緣"),
+    }
+}
+
+/// Localized label for a resampling filter.
+fn resample_filter_label(filter: ResampleFilter, lang: Language) -> &'static str {
+    match filter {
+        ResampleFilter::Nearest => loc(lang, "Nearest (fastest)", "最近鄰（最快）"),
+        ResampleFilter::Triangle => loc(lang, "Bilinear", "雙線性"),
+        ResampleFilter::CatmullRom => loc(lang, "Bicubic", "雙三次"),
+        ResampleFilter::Lanczos3 => loc(lang, "Lanczos (sharpest)", "Lanczos（最銳利）"),
+    }
+}
+
+/// Localized label for an auto-rotate direction.
+fn rotate_direction_label(direction: RotateDirection, lang: Language) -> &'static str {
+    match direction {
+        RotateDirection::Clockwise => loc(lang, "Clockwise", "順時針"),
+        RotateDirection::CounterClockwise => loc(lang, "Counter-clockwise", "逆時針"),
+    }
+}
+
+/// Localized label for an interval mode.
+fn interval_mode_label(mode: IntervalMode, lang: Language) -> &'static str {
+    match mode {
+        IntervalMode::Fixed => loc(lang, "Fixed", "固定"),
+        IntervalMode::Random => loc(lang, "Random range", "隨機範圍"),
+    }
+}
+
+/// Status text for a pause the worker started by itself.
+fn pause_label(reason: &PauseReason, lang: Language) -> String {
+    match reason {
+        PauseReason::OnBattery => loc(lang, "Paused on battery", "使用電池，已暫停").to_string(),
+        PauseReason::AppRunning(app) => {
+            format!(
+                "{} {app}",
+                loc(lang, "Paused while running", "暫停中，正在執行")
+            )
+        }
+    }
+}
+
+/// Pin durations offered in the UI and tray menu.
+fn pin_choices(lang: Language) -> [(PinRequest, &'static str); 3] {
+    [
+        (
+            PinRequest::For(60 * 60),
+            loc(lang, "Keep for 1 hour", "保留 1 小時"),
+        ),
+        (
+            PinRequest::UntilTomorrow,
+            loc(lang, "Keep until tomorrow", "保留到明天"),
+        ),
+        (
+            PinRequest::Indefinitely,
+            loc(lang, "Keep indefinitely", "無限期保留"),
+        ),
+    ]
+}
+
+/// Short weekday names, Monday first.
+fn weekday_labels(lang: Language) -> [&'static str; 7] {
+    match lang {
+        Language::En => ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+        Language::Cht => ["一", "二", "三", "四", "五", "六", "日"],
+    }
+}
+
+/// Edit one schedule boundary: a clock time, or an offset from a sun event.
+fn boundary_editor(
+    ui: &mut egui::Ui,
+    id: &str,
+    anchor: &mut TimeAnchor,
+    minute: &mut i32,
+    lang: Language,
+) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_source(id)
+        .selected_text(anchor_label(*anchor, lang))
+        .show_ui(ui, |ui| {
+            for option in TimeAnchor::ALL {
+                if ui
+                    .selectable_value(anchor, option, anchor_label(option, lang))
+                    .changed()
+                {
+                    // Clock minutes make no sense as an offset, so start sun anchors at zero.
+                    *minute = if option == TimeAnchor::Clock {
+                        7 * 60
+                    } else {
+                        0
+                    };
+                    changed = true;
+                }
+            }
+        });
+    let edited = if *anchor == TimeAnchor::Clock {
+        ui.add(minute_drag(minute))
+    } else {
+        let sign = if *minute >= 0 { "+" } else { "" };
+        ui.add(
+            egui::DragValue::new(minute)
+                .clamp_range(-schedule::MAX_ANCHOR_OFFSET..=schedule::MAX_ANCHOR_OFFSET)
+                .speed(1.0)
+                .prefix(sign)
+                .suffix(loc(lang, " min", " 分")),
+        )
+    };
+    changed || edit_finished(&edited)
+}
+
+/// Drag value that edits minutes after midnight as `HH:MM`.
+fn minute_drag(minute: &mut i32) -> egui::DragValue<'_> {
+    egui::DragValue::new(minute)
+        .clamp_range(0..=24 * 60)
+        .speed(5.0)
+        .custom_formatter(|value, _| schedule::format_minute(value as i32))
+        .custom_parser(|text| schedule::parse_minute(text).map(f64::from))
+}
+
+/// Return true once an edit through `response` is finished: a drag was released,
+/// a text field lost focus (Enter included), or a click changed the value.
+///
+/// Restarting the worker or re-rendering on every drag frame or keystroke would
+/// stall the UI and change the wallpaper over and over.
+fn edit_finished(response: &egui::Response) -> bool {
+    response.drag_stopped()
+        || response.lost_focus()
+        || (response.changed() && !response.dragged() && !response.has_focus())
+}
+
 fn loc(lang: Language, en: &'static str, cht: &'static str) -> &'static str {
     match lang {
         Language::En => en,
@@ -1213,16 +2861,6 @@ fn display_name(path: &Path) -> String {
         .unwrap_or_else(|| path.to_string_lossy().into_owned())
 }
 
-fn source_contains(source: &FolderSource, path: &Path) -> bool {
-    if source.include_subfolders {
-        path.starts_with(&source.path)
-    } else {
-        path.parent()
-            .map(|parent| parent == source.path.as_path())
-            .unwrap_or(false)
-    }
-}
-
 fn section_frame<R>(
     ui: &mut egui::Ui,
     title: &str,
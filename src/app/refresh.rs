@@ -0,0 +1,76 @@
+//! Re-rendering the desktop wallpaper after style edits, off the UI thread.
+
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use eframe::egui;
+
+use super::render_images;
+use crate::image_ops::cached_wallpaper_path;
+use crate::slideshow::SlideshowConfig;
+use crate::wallpaper::set_wallpaper;
+
+/// What to put back on the desktop.
+pub enum Refresh {
+    /// Render these source images with this configuration and show the result.
+    Render(Box<SlideshowConfig>, Vec<PathBuf>),
+    /// Show the cached wallpaper again so a new style takes effect.
+    Reapply,
+}
+
+/// Handle to the thread that carries out [`Refresh`] requests.
+///
+/// Requests that arrive while one is being rendered are collapsed: only the
+/// newest runs next, since it supersedes the others.
+pub struct WallpaperRefresher {
+    requests: Sender<Refresh>,
+    failures: Receiver<String>,
+}
+
+impl WallpaperRefresher {
+    /// Start the refresh thread; `ctx` is repainted when a refresh fails.
+    pub fn start(ctx: egui::Context) -> Self {
+        let (requests, request_rx) = mpsc::channel::<Refresh>();
+        let (failure_tx, failures) = mpsc::channel();
+        thread::spawn(move || {
+            // Ends once the app drops its handle.
+            while let Ok(mut request) = request_rx.recv() {
+                while let Ok(newer) = request_rx.try_recv() {
+                    request = newer;
+                }
+                if let Err(err) = refresh(request) {
+                    let _ = failure_tx.send(err.to_string());
+                    ctx.request_repaint();
+                }
+            }
+        });
+        Self { requests, failures }
+    }
+
+    /// Queue a refresh, replacing any that has not started yet.
+    pub fn request(&self, refresh: Refresh) {
+        let _ = self.requests.send(refresh);
+    }
+
+    /// Error of the latest failed refresh since the last call, if any.
+    pub fn take_failure(&self) -> Option<String> {
+        self.failures.try_iter().last()
+    }
+}
+
+/// Carry out one refresh request.
+fn refresh(request: Refresh) -> anyhow::Result<()> {
+    match request {
+        Refresh::Render(config, images) => set_wallpaper(&render_images(&config, &images)?),
+        Refresh::Reapply => {
+            // Nothing cached yet means nothing was shown to restyle.
+            if let Ok(cache_path) = cached_wallpaper_path()
+                && cache_path.exists()
+            {
+                let _ = set_wallpaper(&cache_path);
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Per-image focal points persisted beside the settings file.
+//!
+//! Each point is stored with a hash of the image's bytes, so a file replaced under
+//! the same path does not inherit a stale point. Hashes are cached per path and
+//! only recomputed when the file's size or modification time changes. Renders
+//! share one parsed copy of the map, replaced whenever the map is saved.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result, anyhow};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+/// File name used under the per-user config directory.
+const FOCUS_FILE: &str = "focus.json";
+
+/// FNV-1a offset basis; a stable hash keeps the file valid across builds.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+/// FNV-1a prime.
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Content hashes by path, shared by the UI and the slideshow worker.
+static HASH_CACHE: LazyLock<Mutex<HashMap<PathBuf, CachedHash>>> = LazyLock::new(Default::default);
+
+/// Saved map as renders see it, parsed on first use.
+static SHARED: Mutex<Option<Arc<FocusMap>>> = Mutex::new(None);
+
+/// Content hash of a file with the size and modification time it was taken at.
+#[derive(Debug, Clone, Copy)]
+struct CachedHash {
+    len: u64,
+    modified: SystemTime,
+    hash: u64,
+}
+
+/// Point of an image that crops keep in frame.
+///
+/// Coordinates are fractions of the upright (EXIF-oriented) image, from the
+/// top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FocusPoint {
+    /// Horizontal position in range [0, 1].
+    pub x: f32,
+    /// Vertical position in range [0, 1].
+    pub y: f32,
+}
+
+impl FocusPoint {
+    /// Build a point, clamping both coordinates into the image.
+    pub fn new(x: f32, y: f32) -> Self {
+        Self {
+            x: x.clamp(0.0, 1.0),
+            y: y.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Stored focal point for one image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FocusRecord {
+    /// Hash of the file contents the point was set on.
+    pub hash: u64,
+    /// The focal point.
+    pub point: FocusPoint,
+}
+
+/// All focal points keyed by absolute path.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct FocusMap {
+    /// Records for images that have a focal point.
+    pub images: HashMap<String, FocusRecord>,
+}
+
+impl FocusMap {
+    /// Focal point of an image, if one was set on its current contents.
+    pub fn point(&self, path: &Path) -> Option<FocusPoint> {
+        let record = self.images.get(&focus_key(path))?;
+        let hash = content_hash(path).ok()?;
+        (record.hash == hash).then_some(record.point)
+    }
+
+    /// Store a focal point for an image, or clear it with `None`.
+    pub fn set(&mut self, path: &Path, point: Option<FocusPoint>) -> Result<()> {
+        let key = focus_key(path);
+        match point {
+            Some(point) => {
+                let hash = content_hash(path)?;
+                self.images.insert(key, FocusRecord { hash, point });
+            }
+            None => {
+                self.images.remove(&key);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Focal point of an image from the saved map.
+pub fn lookup(path: &Path) -> Option<FocusPoint> {
+    shared().point(path)
+}
+
+/// The saved map, parsed once and shared until [`save`] replaces it.
+pub fn shared() -> Arc<FocusMap> {
+    let mut shared = SHARED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    Arc::clone(shared.get_or_insert_with(|| Arc::new(load())))
+}
+
+/// FNV-1a hash of a file's bytes, reused while the file's metadata is unchanged.
+///
+/// Focal points are looked up on every repaint and render, and reading whole
+/// images each time would stall both.
+fn content_hash(path: &Path) -> Result<u64> {
+    let metadata =
+        fs::metadata(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let (len, modified) = (metadata.len(), metadata.modified()?);
+    let cached = lock_hashes().get(path).copied();
+    if let Some(cached) = cached.filter(|cached| cached.len == len && cached.modified == modified) {
+        return Ok(cached.hash);
+    }
+
+    let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let hash = bytes.iter().fold(FNV_OFFSET, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    });
+    lock_hashes().insert(
+        path.to_path_buf(),
+        CachedHash {
+            len,
+            modified,
+            hash,
+        },
+    );
+    Ok(hash)
+}
+
+/// Lock the hash cache, recovering it if another thread panicked.
+fn lock_hashes() -> MutexGuard<'static, HashMap<PathBuf, CachedHash>> {
+    HASH_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Normalize a path into the string used as the map key.
+fn focus_key(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// Build the focus map path and ensure the directory exists.
+fn focus_path() -> Result<PathBuf> {
+    let proj_dirs = ProjectDirs::from("dev", "wallpaper_manager", "wallpaper_manager")
+        .ok_or_else(|| anyhow!("cannot determine config directory"))?;
+    let config_dir = proj_dirs.config_dir();
+    fs::create_dir_all(config_dir)?;
+    Ok(config_dir.join(FOCUS_FILE))
+}
+
+/// Load the focus map from disk, returning an empty map when missing.
+pub fn load() -> FocusMap {
+    let path = match focus_path() {
+        Ok(path) => path,
+        Err(_) => return FocusMap::default(),
+    };
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return FocusMap::default(),
+    };
+    serde_json::from_str(&contents).unwrap_or_default()
+}
+
+/// Persist the focus map to disk as pretty JSON.
+pub fn save(map: &FocusMap) -> Result<()> {
+    let path = focus_path()?;
+    let contents = serde_json::to_string_pretty(map)?;
+    fs::write(path, contents)?;
+    *SHARED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::new(map.clone()));
+    Ok(())
+}
@@ -0,0 +1,256 @@
+//! Photo-wall collages: many images packed into justified rows.
+//!
+//! Rows keep the images' order and aspect ratios; each row is scaled to span the
+//! full width, and the rows are stretched together to fill the height, so every
+//! image loses at most a sliver to cropping.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use image::imageops::{self, FilterType};
+use image::metadata::Orientation;
+use image::{DynamicImage, Rgb, RgbImage};
+
+use super::crop::crop_to_fill;
+use super::decor::decorate;
+use super::render::RenderTarget;
+use super::{StitchOptions, exif_orientation, open_image, write_cache};
+use crate::focus;
+
+/// Fewest images a collage is made of.
+pub const MIN_COLLAGE_IMAGES: usize = 4;
+/// Most images a collage is made of.
+pub const MAX_COLLAGE_IMAGES: usize = 60;
+
+/// Pack `paths` into a collage of the options' output size and cache it.
+///
+/// Uses the crop mode, focal points, letterbox colour and styling of `options`;
+/// its layout, scaling and rotation do not apply.
+pub fn collage_images(
+    paths: &[PathBuf],
+    options: &StitchOptions,
+    render: Option<RenderTarget>,
+) -> Result<PathBuf> {
+    if paths.is_empty() {
+        bail!("no images for the collage");
+    }
+    let (width, height) = options
+        .crop
+        .ok_or_else(|| anyhow!("a collage needs an output size"))?;
+    let style = options.style.fitted(width, height);
+    let (inner_width, inner_height) = style.inner_size(width, height);
+
+    let ratios = paths
+        .iter()
+        .map(|path| aspect_ratio(path))
+        .collect::<Result<Vec<_>>>()?;
+    let cells = justify(&ratios, inner_width, inner_height);
+
+    let focus_map = focus::shared();
+    let mut canvas = RgbImage::from_pixel(inner_width, inner_height, Rgb(options.letterbox.color));
+    for (path, &(x, y, cell_width, cell_height)) in paths.iter().zip(&cells) {
+        // A canvas too small for every image leaves some cells empty.
+        if cell_width == 0 || cell_height == 0 {
+            continue;
+        }
+        let img = open_image(path)?;
+        // Shrink each photo right away so dozens of them never sit in memory at once.
+        let img = if img.width() > 2 * cell_width && img.height() > 2 * cell_height {
+            img.thumbnail(2 * cell_width, 2 * cell_height)
+        } else {
+            img
+        };
+        let tile = crop_to_fill(
+            &img.to_rgb8(),
+            cell_width,
+            cell_height,
+            options.crop_mode,
+            focus_map.point(path),
+            FilterType::Lanczos3,
+        );
+        imageops::replace(&mut canvas, &tile, x as i64, y as i64);
+    }
+
+    let image = if style.is_plain() {
+        canvas
+    } else {
+        decorate(&canvas, &cells, &style)
+    };
+    write_cache(&DynamicImage::ImageRgb8(image), render, None)
+}
+
+/// Width over height of an image as shown, read without decoding it.
+fn aspect_ratio(path: &Path) -> Result<f64> {
+    let (width, height) = image::image_dimensions(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let turned = matches!(
+        exif_orientation(path),
+        Some(
+            Orientation::Rotate90
+                | Orientation::Rotate270
+                | Orientation::Rotate90FlipH
+                | Orientation::Rotate270FlipH
+        )
+    );
+    let (width, height) = if turned {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    Ok(width.max(1) as f64 / height.max(1) as f64)
+}
+
+/// Cells `(x, y, width, height)` of images with the given aspect `ratios`,
+/// packed in order into rows that exactly fill `width` x `height`.
+fn justify(ratios: &[f64], width: u32, height: u32) -> Vec<(u32, u32, u32, u32)> {
+    let rows = split_rows(ratios, width, height);
+
+    // A row of total ratio `sum` is `width / sum` tall at full width.
+    let natural: Vec<f64> = rows
+        .iter()
+        .map(|row| width as f64 / row.iter().sum::<f64>())
+        .collect();
+    let stretch = height as f64 / natural.iter().sum::<f64>();
+
+    let mut cells = Vec::with_capacity(ratios.len());
+    let mut top_exact = 0.0_f64;
+    for (row, row_height) in rows.iter().zip(&natural) {
+        // Edges are rounded rather than sizes, so cells tile the canvas exactly.
+        let top = top_exact.round() as u32;
+        top_exact += row_height * stretch;
+        let bottom = top_exact.round() as u32;
+        let sum: f64 = row.iter().sum();
+        let mut left_exact = 0.0_f64;
+        for ratio in row.iter() {
+            let left = (left_exact / sum * width as f64).round() as u32;
+            left_exact += ratio;
+            let right = (left_exact / sum * width as f64).round() as u32;
+            cells.push((left, top, right - left, bottom - top));
+        }
+    }
+    cells
+}
+
+/// Split `ratios` into consecutive rows whose ratio sums are as even as possible.
+///
+/// The row count makes rows about as tall as the canvas shares out; the split
+/// itself is the classic linear partition, solved exactly.
+fn split_rows(ratios: &[f64], width: u32, height: u32) -> Vec<&[f64]> {
+    let count = ratios.len();
+    let total: f64 = ratios.iter().sum();
+    // `rows` rows of ratio `total / rows` at full width fill the height when
+    // `rows * width * rows / total == height`.
+    let rows =
+        ((total * height as f64 / width.max(1) as f64).sqrt().round() as usize).clamp(1, count);
+    let target = total / rows as f64;
+
+    let mut prefix = vec![0.0; count + 1];
+    for (idx, ratio) in ratios.iter().enumerate() {
+        prefix[idx + 1] = prefix[idx] + ratio;
+    }
+    // cost[k][i]: best cost of the first `i` images in `k` rows; `split` remembers
+    // where the last of those rows starts.
+    let mut cost = vec![vec![f64::INFINITY; count + 1]; rows + 1];
+    let mut split = vec![vec![0; count + 1]; rows + 1];
+    cost[0][0] = 0.0;
+    for k in 1..=rows {
+        for i in k..=count {
+            for j in (k - 1)..i {
+                let deviation = prefix[i] - prefix[j] - target;
+                let candidate = cost[k - 1][j] + deviation * deviation;
+                if candidate < cost[k][i] {
+                    cost[k][i] = candidate;
+                    split[k][i] = j;
+                }
+            }
+        }
+    }
+
+    let mut bounds = vec![count];
+    let mut end = count;
+    for k in (1..=rows).rev() {
+        end = split[k][end];
+        bounds.push(end);
+    }
+    bounds.reverse();
+    bounds
+        .windows(2)
+        .map(|pair| &ratios[pair[0]..pair[1]])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Assert that `cells` cover a `width` x `height` canvas once, in row order.
+    fn assert_tiles(cells: &[(u32, u32, u32, u32)], width: u32, height: u32) {
+        let mut covered = vec![0_u8; (width * height) as usize];
+        for &(x, y, cell_width, cell_height) in cells {
+            assert!(x + cell_width <= width && y + cell_height <= height);
+            for cy in y..y + cell_height {
+                for cx in x..x + cell_width {
+                    covered[(cy * width + cx) as usize] += 1;
+                }
+            }
+        }
+        assert!(covered.iter().all(|&count| count == 1));
+        assert!(cells.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    }
+
+    #[test]
+    fn split_rows_keeps_order_and_balances_rows() {
+        let ratios = [1.5, 1.5, 0.75, 0.75, 1.5, 1.5];
+        // A total ratio of 7.5 on a 16:9 canvas makes two rows.
+        let rows = split_rows(&ratios, 1600, 900);
+        assert_eq!(rows, [&ratios[..3], &ratios[3..]]);
+        assert_eq!(rows.concat(), ratios);
+    }
+
+    #[test]
+    fn split_rows_uses_one_row_per_image_at_most() {
+        let ratios = [0.1, 0.1];
+        let rows = split_rows(&ratios, 100, 10_000);
+        assert_eq!(rows, [&ratios[..1], &ratios[1..]]);
+        assert_eq!(split_rows(&[4.0], 100, 10_000).len(), 1);
+    }
+
+    #[test]
+    fn justify_tiles_the_canvas_exactly() {
+        let ratios = [1.5, 0.667, 1.0, 1.78, 0.75, 2.4, 1.33];
+        let cells = justify(&ratios, 1920, 1080);
+        assert_eq!(cells.len(), ratios.len());
+        assert_tiles(&cells, 1920, 1080);
+
+        let cells = justify(&[1.0; 60], 1001, 333);
+        assert_tiles(&cells, 1001, 333);
+    }
+
+    #[test]
+    fn justify_keeps_rows_close_to_their_aspect_ratios() {
+        let cells = justify(&[1.0, 1.0, 1.0, 1.0], 200, 200);
+        assert_eq!(
+            cells,
+            [
+                (0, 0, 100, 100),
+                (100, 0, 100, 100),
+                (0, 100, 100, 100),
+                (100, 100, 100, 100)
+            ]
+        );
+    }
+
+    #[test]
+    fn justify_survives_a_canvas_smaller_than_the_images() {
+        let cells = justify(&[1.0; 60], 5, 3);
+        assert_eq!(cells.len(), 60);
+        assert_tiles(
+            &cells
+                .into_iter()
+                .filter(|&(_, _, width, height)| width > 0 && height > 0)
+                .collect::<Vec<_>>(),
+            5,
+            3,
+        );
+    }
+}
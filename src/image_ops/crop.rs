@@ -0,0 +1,251 @@
+//! Scaling an image to cover a frame and choosing which part of it to keep.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, RgbImage};
+
+use crate::focus::FocusPoint;
+use crate::settings::CropMode;
+
+/// Longest side of the copy that edge energy is measured on.
+const ENERGY_SAMPLE: u32 = 256;
+
+/// How [`crop_to_fill_mapped`] turned source pixels into frame pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CropWindow {
+    /// Horizontal scale applied to the source.
+    pub scale_x: f64,
+    /// Vertical scale applied to the source.
+    pub scale_y: f64,
+    /// Left edge of the frame on the scaled source.
+    pub x: u32,
+    /// Top edge of the frame on the scaled source.
+    pub y: u32,
+}
+
+impl CropWindow {
+    /// Window that leaves the source as it is.
+    pub const IDENTITY: CropWindow = CropWindow {
+        scale_x: 1.0,
+        scale_y: 1.0,
+        x: 0,
+        y: 0,
+    };
+
+    /// Frame position `(x, y)` of a source position.
+    pub fn map(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            x * self.scale_x - self.x as f64,
+            y * self.scale_y - self.y as f64,
+        )
+    }
+}
+
+/// Scale `img` to cover `width` x `height` and cut the frame out of it.
+///
+/// A `focus` point is kept as close to the middle of the frame as the image
+/// allows. Without one, the window is centred along the axis with slack, or
+/// placed where the most detail is when `mode` is [`CropMode::Smart`].
+pub fn crop_to_fill(
+    img: &RgbImage,
+    width: u32,
+    height: u32,
+    mode: CropMode,
+    focus: Option<FocusPoint>,
+    filter: FilterType,
+) -> RgbImage {
+    crop_to_fill_mapped(img, width, height, mode, focus, filter).0
+}
+
+/// [`crop_to_fill`], also returning where the frame came from.
+pub fn crop_to_fill_mapped(
+    img: &RgbImage,
+    width: u32,
+    height: u32,
+    mode: CropMode,
+    focus: Option<FocusPoint>,
+    filter: FilterType,
+) -> (RgbImage, CropWindow) {
+    if img.width() == 0 || img.height() == 0 || width == 0 || height == 0 {
+        return (img.clone(), CropWindow::IDENTITY);
+    }
+
+    // Use the larger scale factor so the image covers the target area
+    let scale = (width as f64 / img.width() as f64).max(height as f64 / img.height() as f64);
+    let scaled_width = ((img.width() as f64 * scale).round() as u32).max(width);
+    let scaled_height = ((img.height() as f64 * scale).round() as u32).max(height);
+    let scaled = imageops::resize(img, scaled_width, scaled_height, filter);
+
+    let (x, y) = match (focus, mode) {
+        (Some(focus), _) => (
+            focus_offset(focus.x, scaled_width, width),
+            focus_offset(focus.y, scaled_height, height),
+        ),
+        (None, CropMode::Center) => ((scaled_width - width) / 2, (scaled_height - height) / 2),
+        (None, CropMode::Smart) => smart_offset(&scaled, width, height),
+    };
+    let window = CropWindow {
+        scale_x: scaled_width as f64 / img.width() as f64,
+        scale_y: scaled_height as f64 / img.height() as f64,
+        x,
+        y,
+    };
+    (
+        imageops::crop_imm(&scaled, x, y, width, height).to_image(),
+        window,
+    )
+}
+
+/// Start of a `window` long span of `size` centred on the fraction `at`.
+fn focus_offset(at: f32, size: u32, window: u32) -> u32 {
+    let start = at as f64 * size as f64 - window as f64 / 2.0;
+    (start.round().max(0.0) as u32).min(size - window)
+}
+
+/// Offset of the `width` x `height` window holding the most edge energy.
+///
+/// Only one axis has slack once the image covers the frame, so the search is a
+/// single slide along it; ties keep the window closest to the centre.
+fn smart_offset(img: &RgbImage, width: u32, height: u32) -> (u32, u32) {
+    let horizontal = img.width() - width >= img.height() - height;
+    let (size, window) = if horizontal {
+        (img.width(), width)
+    } else {
+        (img.height(), height)
+    };
+    if size == window {
+        return (0, 0);
+    }
+
+    let profile = energy_profile(img, horizontal);
+    let ratio = profile.len() as f64 / size as f64;
+    let sample_window = ((window as f64 * ratio).round() as usize).clamp(1, profile.len());
+
+    // Prefix sums turn every window total into one subtraction.
+    let mut prefix = vec![0_u64; profile.len() + 1];
+    for (idx, energy) in profile.iter().enumerate() {
+        prefix[idx + 1] = prefix[idx] + energy;
+    }
+    let centre = (profile.len() - sample_window) as f64 / 2.0;
+    let best = (0..=profile.len() - sample_window)
+        .max_by(|&a, &b| {
+            let total = |start: usize| prefix[start + sample_window] - prefix[start];
+            total(a).cmp(&total(b)).then_with(|| {
+                (b as f64 - centre)
+                    .abs()
+                    .total_cmp(&(a as f64 - centre).abs())
+            })
+        })
+        .unwrap_or(0);
+
+    let offset = ((best as f64 / ratio).round() as u32).min(size - window);
+    if horizontal { (offset, 0) } else { (0, offset) }
+}
+
+/// Edge energy summed per column (`horizontal`) or per row of a small luma copy.
+fn energy_profile(img: &RgbImage, horizontal: bool) -> Vec<u64> {
+    let luma = DynamicImage::ImageRgb8(img.clone())
+        .thumbnail(ENERGY_SAMPLE, ENERGY_SAMPLE)
+        .to_luma8();
+    let (w, h) = luma.dimensions();
+    let mut profile = vec![0_u64; if horizontal { w } else { h } as usize];
+    for y in 0..h {
+        for x in 0..w {
+            let at = |x: u32, y: u32| luma.get_pixel(x, y).0[0] as i32;
+            let dx = at((x + 1).min(w - 1), y) - at(x.saturating_sub(1), y);
+            let dy = at(x, (y + 1).min(h - 1)) - at(x, y.saturating_sub(1));
+            let idx = if horizontal { x } else { y } as usize;
+            profile[idx] += (dx.unsigned_abs() + dy.unsigned_abs()) as u64;
+        }
+    }
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// Flat grey image with a checkerboard over columns (or rows) `detail`.
+    fn detailed(width: u32, height: u32, detail: std::ops::Range<u32>, columns: bool) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            let along = if columns { x } else { y };
+            if detail.contains(&along) && (x / 2 + y / 2) % 2 == 0 {
+                Rgb([255, 255, 255])
+            } else {
+                Rgb([128, 128, 128])
+            }
+        })
+    }
+
+    fn window(img: &RgbImage, width: u32, height: u32, mode: CropMode) -> CropWindow {
+        crop_to_fill_mapped(img, width, height, mode, None, FilterType::Nearest).1
+    }
+
+    #[test]
+    fn center_crop_cuts_the_axis_with_slack_evenly() {
+        let img = detailed(200, 100, 0..0, true);
+        let (frame, at) =
+            crop_to_fill_mapped(&img, 50, 50, CropMode::Center, None, FilterType::Nearest);
+        assert_eq!(frame.dimensions(), (50, 50));
+        assert_eq!((at.scale_x, at.scale_y, at.x, at.y), (0.5, 0.5, 25, 0));
+        assert_eq!(at.map(100.0, 50.0), (25.0, 25.0));
+    }
+
+    #[test]
+    fn smart_crop_follows_the_edge_energy() {
+        let right = detailed(300, 100, 220..300, true);
+        assert_eq!(window(&right, 100, 100, CropMode::Smart).x, 200);
+        let left = detailed(300, 100, 0..60, true);
+        assert_eq!(window(&left, 100, 100, CropMode::Smart).x, 0);
+        let bottom = detailed(100, 300, 240..300, false);
+        let at = window(&bottom, 100, 100, CropMode::Smart);
+        assert_eq!((at.x, at.y), (0, 200));
+    }
+
+    #[test]
+    fn smart_crop_of_a_flat_image_stays_centred() {
+        let flat = detailed(300, 100, 0..0, true);
+        // Within one energy sample of the exact centre.
+        assert!(window(&flat, 100, 100, CropMode::Smart).x.abs_diff(100) <= 1);
+        // Nothing to slide when the aspect ratios already match.
+        assert_eq!(
+            window(&flat, 150, 50, CropMode::Smart),
+            CropWindow {
+                scale_x: 0.5,
+                scale_y: 0.5,
+                x: 0,
+                y: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn focus_point_wins_over_the_mode_and_stays_inside() {
+        let img = detailed(300, 100, 220..300, true);
+        let crop = |x: f32| {
+            let focus = FocusPoint { x, y: 0.5 };
+            crop_to_fill_mapped(
+                &img,
+                100,
+                100,
+                CropMode::Smart,
+                Some(focus),
+                FilterType::Nearest,
+            )
+            .1
+            .x
+        };
+        assert_eq!(crop(0.0), 0);
+        assert_eq!(crop(0.5), 100);
+        assert_eq!(crop(1.0), 200);
+    }
+
+    #[test]
+    fn empty_frames_leave_the_image_alone() {
+        let img = detailed(30, 20, 0..0, true);
+        let (frame, at) =
+            crop_to_fill_mapped(&img, 0, 10, CropMode::Smart, None, FilterType::Nearest);
+        assert_eq!(frame, img);
+        assert_eq!(at, CropWindow::IDENTITY);
+    }
+}
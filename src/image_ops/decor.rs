@@ -0,0 +1,247 @@
+//! Gutters, margins, rounded corners and shadows for stitched composites.
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Luma, Rgb, RgbImage};
+
+/// Downscale factor applied to the shadow mask before blurring.
+const SHADOW_DOWNSCALE: u32 = 4;
+/// Shadow blur radius (sigma) in output pixels.
+const SHADOW_BLUR: f32 = 12.0;
+/// Shadow offset in output pixels, down and to the right.
+const SHADOW_OFFSET: u32 = 6;
+/// Share of the background's brightness the darkest shadow removes.
+const SHADOW_STRENGTH: f32 = 0.6;
+
+/// Look of the seams between stitched images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StitchStyle {
+    /// Gap between neighbouring images in pixels.
+    pub gap: u32,
+    /// Margin around the whole composite in pixels.
+    pub margin: u32,
+    /// Colour of the gaps and margin.
+    pub color: [u8; 3],
+    /// Corner radius of each image in pixels.
+    pub corner_radius: u32,
+    /// Whether images cast a soft shadow onto the background.
+    pub shadow: bool,
+}
+
+impl StitchStyle {
+    /// Return true when the style leaves the composite untouched.
+    pub fn is_plain(&self) -> bool {
+        self.gap == 0 && self.margin == 0 && self.corner_radius == 0 && !self.shadow
+    }
+
+    /// The style with its margin shrunk so a `width` x `height` output keeps at
+    /// least one pixel for the images.
+    pub fn fitted(&self, width: u32, height: u32) -> Self {
+        Self {
+            margin: self.margin.min(width.min(height).saturating_sub(1) / 2),
+            ..*self
+        }
+    }
+
+    /// Size left for the images inside the margin.
+    pub fn inner_size(&self, width: u32, height: u32) -> (u32, u32) {
+        (
+            width.saturating_sub(2 * self.margin),
+            height.saturating_sub(2 * self.margin),
+        )
+    }
+}
+
+/// Redraw the cells of `img` as separate tiles on the style's background.
+///
+/// `cells` are `(x, y, width, height)` rectangles on `img`. Gaps are taken from
+/// the cells' inner edges, so images keep their positions; the result is `img`
+/// grown by the margin on every side.
+pub fn decorate(img: &RgbImage, cells: &[(u32, u32, u32, u32)], style: &StitchStyle) -> RgbImage {
+    let margin = style.margin;
+    let tiles: Vec<(u32, u32, u32, u32)> = cells
+        .iter()
+        .filter_map(|&cell| inset(cell, img.width(), img.height(), style.gap))
+        .collect();
+
+    let mut result = RgbImage::from_pixel(
+        img.width() + 2 * margin,
+        img.height() + 2 * margin,
+        Rgb(style.color),
+    );
+    if style.shadow {
+        cast_shadow(&mut result, &tiles, margin);
+    }
+    for &(x, y, width, height) in &tiles {
+        let radius = style.corner_radius.min(width / 2).min(height / 2) as f32;
+        for ty in 0..height {
+            for tx in 0..width {
+                let coverage = corner_coverage(tx, ty, width, height, radius);
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let src = img.get_pixel(x + tx, y + ty);
+                let dst = result.get_pixel_mut(x + tx + margin, y + ty + margin);
+                for (out, &value) in dst.0.iter_mut().zip(&src.0) {
+                    *out = (value as f32 * coverage + *out as f32 * (1.0 - coverage)).round() as u8;
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Shrink a cell by half the gap on each side that touches another cell.
+fn inset(
+    (x, y, width, height): (u32, u32, u32, u32),
+    canvas_width: u32,
+    canvas_height: u32,
+    gap: u32,
+) -> Option<(u32, u32, u32, u32)> {
+    // The two halves add up to the whole gap even when it is odd.
+    let left = if x > 0 { gap / 2 } else { 0 };
+    let top = if y > 0 { gap / 2 } else { 0 };
+    let right = if x + width < canvas_width {
+        gap - gap / 2
+    } else {
+        0
+    };
+    let bottom = if y + height < canvas_height {
+        gap - gap / 2
+    } else {
+        0
+    };
+    let width = width.checked_sub(left + right).filter(|&w| w > 0)?;
+    let height = height.checked_sub(top + bottom).filter(|&h| h > 0)?;
+    Some((x + left, y + top, width, height))
+}
+
+/// Share of pixel (`x`, `y`) inside a tile whose corners are rounded by `radius`.
+fn corner_coverage(x: u32, y: u32, width: u32, height: u32, radius: f32) -> f32 {
+    if radius <= 0.0 {
+        return 1.0;
+    }
+    // Distance from the pixel centre into the corner square, 0 outside the corners.
+    let reach = |at: u32, size: u32| {
+        let centre = at as f32 + 0.5;
+        (radius - centre)
+            .max(centre - (size as f32 - radius))
+            .max(0.0)
+    };
+    let (dx, dy) = (reach(x, width), reach(y, height));
+    if dx == 0.0 || dy == 0.0 {
+        return 1.0;
+    }
+    (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0)
+}
+
+/// Darken the background under the tiles with a soft, offset shadow.
+fn cast_shadow(canvas: &mut RgbImage, tiles: &[(u32, u32, u32, u32)], margin: u32) {
+    // Blurring a small mask is far cheaper and looks the same once scaled back up.
+    let small_width = (canvas.width() / SHADOW_DOWNSCALE).max(1);
+    let small_height = (canvas.height() / SHADOW_DOWNSCALE).max(1);
+    let mut mask = GrayImage::new(small_width, small_height);
+    for &(x, y, width, height) in tiles {
+        let left = (x + margin + SHADOW_OFFSET) / SHADOW_DOWNSCALE;
+        let top = (y + margin + SHADOW_OFFSET) / SHADOW_DOWNSCALE;
+        let right = ((x + margin + SHADOW_OFFSET + width) / SHADOW_DOWNSCALE).min(small_width);
+        let bottom = ((y + margin + SHADOW_OFFSET + height) / SHADOW_DOWNSCALE).min(small_height);
+        for my in top..bottom {
+            for mx in left..right {
+                mask.put_pixel(mx, my, Luma([255]));
+            }
+        }
+    }
+    let mask = imageops::blur(&mask, SHADOW_BLUR / SHADOW_DOWNSCALE as f32);
+    let mask = imageops::resize(&mask, canvas.width(), canvas.height(), FilterType::Triangle);
+    for (pixel, shade) in canvas.pixels_mut().zip(mask.pixels()) {
+        let keep = 1.0 - SHADOW_STRENGTH * shade.0[0] as f32 / 255.0;
+        for channel in pixel.0.iter_mut() {
+            *channel = (*channel as f32 * keep) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+    const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+
+    fn style(gap: u32, margin: u32, corner_radius: u32, shadow: bool) -> StitchStyle {
+        StitchStyle {
+            gap,
+            margin,
+            color: [0, 0, 0],
+            corner_radius,
+            shadow,
+        }
+    }
+
+    #[test]
+    fn fitted_margin_leaves_room_for_the_images() {
+        let fitted = style(0, 100, 0, false).fitted(50, 30);
+        assert_eq!(fitted.margin, 14);
+        assert_eq!(fitted.inner_size(50, 30), (22, 2));
+        assert_eq!(style(0, 5, 0, false).fitted(50, 30).margin, 5);
+        assert_eq!(style(0, 5, 0, false).fitted(0, 0).margin, 0);
+    }
+
+    #[test]
+    fn inset_splits_odd_gaps_between_neighbours() {
+        // Outer edges keep their position; the 5px gap is shared 2 + 3.
+        assert_eq!(inset((0, 0, 50, 50), 100, 50, 5), Some((0, 0, 47, 50)));
+        assert_eq!(inset((50, 0, 50, 50), 100, 50, 5), Some((52, 0, 48, 50)));
+        assert_eq!(inset((0, 0, 100, 50), 100, 50, 5), Some((0, 0, 100, 50)));
+    }
+
+    #[test]
+    fn inset_drops_cells_swallowed_by_the_gap() {
+        assert_eq!(inset((10, 0, 4, 50), 100, 50, 4), None);
+        assert_eq!(inset((10, 0, 5, 50), 100, 50, 4), Some((12, 0, 1, 50)));
+    }
+
+    #[test]
+    fn decorate_adds_margin_and_gap_around_tiles() {
+        let img = RgbImage::from_pixel(100, 50, WHITE);
+        let cells = [(0, 0, 50, 50), (50, 0, 50, 50)];
+        let result = decorate(&img, &cells, &style(4, 3, 0, false));
+
+        assert_eq!(result.dimensions(), (106, 56));
+        assert_eq!(*result.get_pixel(0, 0), BLACK);
+        assert_eq!(*result.get_pixel(105, 55), BLACK);
+        assert_eq!(*result.get_pixel(3, 3), WHITE);
+        // The gap spans image columns 48..52, shifted right by the margin.
+        assert_eq!(*result.get_pixel(3 + 47, 20), WHITE);
+        assert_eq!(*result.get_pixel(3 + 48, 20), BLACK);
+        assert_eq!(*result.get_pixel(3 + 51, 20), BLACK);
+        assert_eq!(*result.get_pixel(3 + 52, 20), WHITE);
+    }
+
+    #[test]
+    fn rounded_corners_fade_to_the_background() {
+        assert_eq!(corner_coverage(0, 0, 20, 20, 5.0), 0.0);
+        assert_eq!(corner_coverage(19, 19, 20, 20, 5.0), 0.0);
+        assert_eq!(corner_coverage(10, 0, 20, 20, 5.0), 1.0);
+        assert_eq!(corner_coverage(0, 0, 20, 20, 0.0), 1.0);
+
+        let img = RgbImage::from_pixel(20, 20, WHITE);
+        let result = decorate(&img, &[(0, 0, 20, 20)], &style(0, 0, 50, false));
+        assert_eq!(*result.get_pixel(0, 0), BLACK);
+        assert_eq!(*result.get_pixel(10, 10), WHITE);
+    }
+
+    #[test]
+    fn shadow_darkens_only_below_and_right_of_tiles() {
+        let img = RgbImage::from_pixel(40, 40, WHITE);
+        let mut grey = style(0, 40, 0, true);
+        grey.color = [200, 200, 200];
+        let result = decorate(&img, &[(0, 0, 40, 40)], &grey);
+
+        let far = result.get_pixel(2, 2).0[0];
+        let under = result.get_pixel(40 + 40 + 2, 40 + 40 + 2).0[0];
+        assert_eq!(far, 200);
+        assert!(under < 200, "shadow left {under}");
+        assert_eq!(*result.get_pixel(60, 60), WHITE);
+    }
+}
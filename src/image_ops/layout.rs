@@ -0,0 +1,409 @@
+//! Stitch layouts: rows or columns of weighted slots.
+//!
+//! A layout splits the canvas into lines by their weights, then splits each line
+//! into slots by theirs. Images fill the slots in reading order.
+
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::StitchOrientation;
+
+/// Most slots a layout may have.
+pub const MAX_LAYOUT_SLOTS: usize = 16;
+
+/// Whether a layout's lines run across the canvas or down it.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LayoutDirection {
+    /// Lines are rows stacked top to bottom; slots run left to right.
+    Rows,
+    /// Lines are columns placed left to right; slots run top to bottom.
+    Columns,
+}
+
+/// Orientation an image is turned to for its slot when auto-rotate is on.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SlotOrientation {
+    /// Keep the image as it is.
+    Any,
+    /// Taller than wide.
+    Portrait,
+    /// Wider than tall.
+    Landscape,
+}
+
+/// One image position within a line.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LayoutSlot {
+    /// Share of the line's length, relative to the other slots.
+    pub weight: f32,
+    /// Target orientation of the image placed here.
+    pub orientation: SlotOrientation,
+}
+
+/// One row or column of a layout.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LayoutLine {
+    /// Share of the canvas, relative to the other lines.
+    pub weight: f32,
+    /// Slots in reading order.
+    pub slots: Vec<LayoutSlot>,
+}
+
+/// A named arrangement of image slots.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct StitchLayout {
+    /// Name shown in the layout picker; also the saved selection.
+    pub name: String,
+    /// Whether lines are rows or columns.
+    pub direction: LayoutDirection,
+    /// Lines in reading order.
+    pub lines: Vec<LayoutLine>,
+}
+
+/// A slot's area as fractions of the canvas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    /// Left edge in range [0, 1].
+    pub x: f64,
+    /// Top edge in range [0, 1].
+    pub y: f64,
+    /// Width in range [0, 1].
+    pub width: f64,
+    /// Height in range [0, 1].
+    pub height: f64,
+}
+
+impl Frame {
+    /// Pixel rectangle `(x, y, width, height)` of the frame on a canvas.
+    ///
+    /// Edges are rounded rather than sizes, so neighbouring frames tile exactly.
+    pub fn pixels(&self, canvas_width: u32, canvas_height: u32) -> (u32, u32, u32, u32) {
+        let edge = |at: f64, size: u32| (at * size as f64).round() as u32;
+        let left = edge(self.x, canvas_width);
+        let top = edge(self.y, canvas_height);
+        let right = edge(self.x + self.width, canvas_width);
+        let bottom = edge(self.y + self.height, canvas_height);
+        (left, top, right - left, bottom - top)
+    }
+}
+
+impl StitchLayout {
+    /// Build a layout of equally weighted lines from slot orientations.
+    fn even(name: &str, direction: LayoutDirection, lines: &[&[SlotOrientation]]) -> Self {
+        Self {
+            name: name.to_string(),
+            direction,
+            lines: lines
+                .iter()
+                .map(|slots| LayoutLine {
+                    weight: 1.0,
+                    slots: slots
+                        .iter()
+                        .map(|&orientation| LayoutSlot {
+                            weight: 1.0,
+                            orientation,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Built-in layout for `count` images stitched in `orientation`.
+    pub fn automatic(orientation: StitchOrientation, count: usize) -> Self {
+        use LayoutDirection::{Columns, Rows};
+        use SlotOrientation::{Landscape as L, Portrait as P};
+
+        let name = "Automatic";
+        match orientation {
+            StitchOrientation::Horizontal => match count {
+                2 => Self::even(name, Rows, &[&[P, P]]),
+                3 => Self::even(name, Rows, &[&[P, L, P]]),
+                4 => Self::even(name, Rows, &[&[L, L], &[L, L]]),
+                5 => Self::even(name, Rows, &[&[P, P, P], &[L, L]]),
+                _ => Self::even(name, Rows, &[&vec![P; count]]),
+            },
+            StitchOrientation::Vertical => match count {
+                2 => Self::even(name, Columns, &[&[L, L]]),
+                3 => Self::even(name, Columns, &[&[L, L, L]]),
+                4 => Self::even(name, Rows, &[&[P, P], &[P, P]]),
+                5 => Self::even(name, Rows, &[&[L, L, L], &[P, P]]),
+                _ => Self::even(name, Columns, &[&vec![L; count]]),
+            },
+        }
+    }
+
+    /// Ready-made layouts offered next to the user's own.
+    pub fn presets() -> Vec<Self> {
+        use LayoutDirection::{Columns, Rows};
+        use SlotOrientation::{Any as A, Landscape as L, Portrait as P};
+
+        let mut big_and_four = Self::even("1 big + 4 small", Columns, &[&[A], &[L, L], &[L, L]]);
+        big_and_four.lines[0].weight = 2.0;
+        vec![
+            Self::even("2×2 grid", Rows, &[&[L, L], &[L, L]]),
+            Self::even("3×3 grid", Rows, &[&[L, L, L], &[L, L, L], &[L, L, L]]),
+            big_and_four,
+            Self::even("3 + 2", Rows, &[&[P, P, P], &[L, L]]),
+        ]
+    }
+
+    /// Total number of slots.
+    pub fn slot_count(&self) -> usize {
+        self.lines.iter().map(|line| line.slots.len()).sum()
+    }
+
+    /// Return true when any line or slot has a weight other than 1, so slot sizes
+    /// follow the weights rather than the images.
+    pub fn is_weighted(&self) -> bool {
+        self.lines
+            .iter()
+            .any(|line| line.weight != 1.0 || line.slots.iter().any(|slot| slot.weight != 1.0))
+    }
+
+    /// Slots in reading order.
+    pub fn slots(&self) -> impl Iterator<Item = &LayoutSlot> {
+        self.lines.iter().flat_map(|line| line.slots.iter())
+    }
+
+    /// Area of every slot, in reading order.
+    pub fn frames(&self) -> Vec<Frame> {
+        let line_total: f32 = self.lines.iter().map(|line| line.weight).sum();
+        let mut frames = Vec::with_capacity(self.slot_count());
+        let mut line_start = 0.0;
+        for line in &self.lines {
+            let line_size = (line.weight / line_total) as f64;
+            let slot_total: f32 = line.slots.iter().map(|slot| slot.weight).sum();
+            let mut slot_start = 0.0;
+            for slot in &line.slots {
+                let slot_size = (slot.weight / slot_total) as f64;
+                frames.push(match self.direction {
+                    LayoutDirection::Rows => Frame {
+                        x: slot_start,
+                        y: line_start,
+                        width: slot_size,
+                        height: line_size,
+                    },
+                    LayoutDirection::Columns => Frame {
+                        x: line_start,
+                        y: slot_start,
+                        width: line_size,
+                        height: slot_size,
+                    },
+                });
+                slot_start += slot_size;
+            }
+            line_start += line_size;
+        }
+        frames
+    }
+
+    /// Parse the text form used by the layout editor.
+    ///
+    /// The first line is `rows` or `columns`; each further line (or `/`-separated
+    /// part) is one row or column: an optional `weight:` followed by its slots. A
+    /// slot is `P` (portrait), `L` (landscape) or `A` (any), optionally prefixed
+    /// by a weight, e.g. `columns / 2: A / L L / L L`.
+    pub fn parse(name: &str, spec: &str) -> Result<Self> {
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("Layout needs a name");
+        }
+        let mut parts = spec
+            .split(['\n', '/'])
+            .map(str::trim)
+            .filter(|part| !part.is_empty());
+        let direction = match parts.next().map(str::to_ascii_lowercase).as_deref() {
+            Some("rows") => LayoutDirection::Rows,
+            Some("columns") => LayoutDirection::Columns,
+            _ => bail!("Layout must start with `rows` or `columns`"),
+        };
+        let lines = parts.map(parse_line).collect::<Result<Vec<_>>>()?;
+        let layout = Self {
+            name: name.to_string(),
+            direction,
+            lines,
+        };
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    /// Check what [`StitchLayout::parse`] guarantees, for layouts read from elsewhere
+    /// such as a hand-edited settings file.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            bail!("Layout needs a name");
+        }
+        if self.lines.is_empty() {
+            bail!("Layout has no rows or columns");
+        }
+        if self.lines.iter().any(|line| line.slots.is_empty()) {
+            bail!("Layout has a row or column without slots");
+        }
+        if self.slot_count() > MAX_LAYOUT_SLOTS {
+            bail!("Layout has more than {MAX_LAYOUT_SLOTS} slots");
+        }
+        let valid = |weight: f32| weight.is_finite() && weight > 0.0;
+        let weights_valid = self
+            .lines
+            .iter()
+            .all(|line| valid(line.weight) && line.slots.iter().all(|slot| valid(slot.weight)));
+        if !weights_valid {
+            bail!("Layout weights must be positive");
+        }
+        Ok(())
+    }
+
+    /// Text form accepted by [`StitchLayout::parse`].
+    pub fn spec(&self) -> String {
+        let mut spec = match self.direction {
+            LayoutDirection::Rows => "rows".to_string(),
+            LayoutDirection::Columns => "columns".to_string(),
+        };
+        for line in &self.lines {
+            spec.push('\n');
+            if line.weight != 1.0 {
+                spec.push_str(&format!("{}: ", line.weight));
+            }
+            let slots: Vec<String> = line
+                .slots
+                .iter()
+                .map(|slot| {
+                    let letter = match slot.orientation {
+                        SlotOrientation::Any => "A",
+                        SlotOrientation::Portrait => "P",
+                        SlotOrientation::Landscape => "L",
+                    };
+                    if slot.weight == 1.0 {
+                        letter.to_string()
+                    } else {
+                        format!("{}{letter}", slot.weight)
+                    }
+                })
+                .collect();
+            spec.push_str(&slots.join(" "));
+        }
+        spec
+    }
+}
+
+/// Parse one row or column of the text form.
+fn parse_line(text: &str) -> Result<LayoutLine> {
+    let (weight, slots) = match text.split_once(':') {
+        Some((weight, slots)) => (parse_weight(weight.trim())?, slots),
+        None => (1.0, text),
+    };
+    let slots = slots
+        .split_whitespace()
+        .map(parse_slot)
+        .collect::<Result<Vec<_>>>()?;
+    if slots.is_empty() {
+        bail!("`{text}` has no slots");
+    }
+    Ok(LayoutLine { weight, slots })
+}
+
+/// Parse one slot such as `P`, `2L` or `0.5A`.
+fn parse_slot(text: &str) -> Result<LayoutSlot> {
+    let split = text.len() - text.chars().last().map_or(0, char::len_utf8);
+    let (weight, letter) = text.split_at(split);
+    let orientation = match letter.to_ascii_uppercase().as_str() {
+        "A" => SlotOrientation::Any,
+        "P" => SlotOrientation::Portrait,
+        "L" => SlotOrientation::Landscape,
+        _ => bail!("Unknown slot `{text}`; use P, L or A"),
+    };
+    let weight = if weight.is_empty() {
+        1.0
+    } else {
+        parse_weight(weight)?
+    };
+    Ok(LayoutSlot {
+        weight,
+        orientation,
+    })
+}
+
+/// Parse a positive weight.
+fn parse_weight(text: &str) -> Result<f32> {
+    text.parse::<f32>()
+        .ok()
+        .filter(|weight| weight.is_finite() && *weight > 0.0)
+        .ok_or_else(|| anyhow!("`{text}` is not a positive weight"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_line_and_slot_weights() {
+        let layout = StitchLayout::parse(" Big left ", "columns\n2: A\nL 0.5L\nL L").unwrap();
+        assert_eq!(layout.name, "Big left");
+        assert_eq!(layout.direction, LayoutDirection::Columns);
+        let weights: Vec<f32> = layout.lines.iter().map(|line| line.weight).collect();
+        assert_eq!(weights, [2.0, 1.0, 1.0]);
+        assert_eq!(layout.lines[1].slots[1].weight, 0.5);
+        assert_eq!(
+            layout.lines[1].slots[1].orientation,
+            SlotOrientation::Landscape
+        );
+        assert_eq!(layout.slot_count(), 5);
+        assert!(layout.is_weighted());
+    }
+
+    #[test]
+    fn parse_accepts_slashes_for_line_breaks() {
+        let slashed = StitchLayout::parse("grid", "ROWS / l l / p p").unwrap();
+        let broken = StitchLayout::parse("grid", "rows\nL L\n\nP P\n").unwrap();
+        assert_eq!(slashed, broken);
+        assert!(!slashed.is_weighted());
+    }
+
+    #[test]
+    fn parse_rejects_malformed_layouts() {
+        assert!(StitchLayout::parse("", "rows / A").is_err());
+        assert!(StitchLayout::parse("x", "diagonal / A").is_err());
+        assert!(StitchLayout::parse("x", "rows").is_err());
+        assert!(StitchLayout::parse("x", "rows / A X").is_err());
+        assert!(StitchLayout::parse("x", "rows / 0: A").is_err());
+        assert!(StitchLayout::parse("x", "rows / -1A").is_err());
+        assert!(StitchLayout::parse("x", "rows / 2:").is_err());
+    }
+
+    #[test]
+    fn parse_limits_slot_count() {
+        let full = format!("rows / {}", vec!["A"; MAX_LAYOUT_SLOTS].join(" "));
+        assert_eq!(
+            StitchLayout::parse("x", &full).unwrap().slot_count(),
+            MAX_LAYOUT_SLOTS
+        );
+        let over = format!("{full} / A");
+        assert!(StitchLayout::parse("x", &over).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_weights_parse_would_refuse() {
+        for weight in [f32::NAN, f32::INFINITY, -1.0, 0.0] {
+            let mut line_weight = StitchLayout::parse("x", "rows / A A / A").unwrap();
+            line_weight.lines[1].weight = weight;
+            assert!(line_weight.validate().is_err(), "line weight {weight}");
+            let mut slot_weight = StitchLayout::parse("x", "rows / A A / A").unwrap();
+            slot_weight.lines[0].slots[1].weight = weight;
+            assert!(slot_weight.validate().is_err(), "slot weight {weight}");
+        }
+        let mut empty_line = StitchLayout::parse("x", "rows / A").unwrap();
+        empty_line.lines[0].slots.clear();
+        assert!(empty_line.validate().is_err());
+    }
+
+    #[test]
+    fn spec_round_trips_through_parse() {
+        let mut layouts = StitchLayout::presets();
+        layouts.push(StitchLayout::parse("weighted", "rows / 1.5: 2P L / A 0.25A").unwrap());
+        for layout in layouts {
+            let parsed = StitchLayout::parse(&layout.name, &layout.spec()).unwrap();
+            assert_eq!(parsed, layout, "{}", layout.spec());
+        }
+    }
+}
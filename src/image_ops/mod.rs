@@ -1,15 +1,33 @@
 //! Image discovery and processing utilities.
 
+pub mod collage;
+pub mod crop;
+pub mod decor;
+pub mod layout;
+pub mod render;
+pub mod span;
+
+use std::cmp::Ordering;
 use std::ffi::OsStr;
+use std::fs::File;
+use std::io::BufReader;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use anyhow::{Context, Result, anyhow};
 use directories::ProjectDirs;
-use image::{DynamicImage, ImageFormat, RgbImage};
-use rand::seq::SliceRandom;
+use image::imageops::{self, FilterType};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
 use walkdir::WalkDir;
 
-use crate::settings::StitchOrientation;
+use crop::{CropWindow, crop_to_fill, crop_to_fill_mapped};
+use decor::{StitchStyle, decorate};
+use layout::{Frame, LayoutDirection, SlotOrientation, StitchLayout};
+use render::{Letterbox, RenderTarget, letterbox, render_frame};
+
+use crate::focus::{self, FocusPoint};
+use crate::settings::{CropMode, RotateDirection, SortMode, StitchScaling};
 
 /// Clean up temporary cache files.
 pub fn cleanup_temp_files() {
@@ -40,6 +58,23 @@ pub struct FolderSource {
     pub path: PathBuf,
     /// Whether to include subfolders when scanning.
     pub include_subfolders: bool,
+    /// Relative weight when random selection picks a folder first.
+    pub weight: u32,
+    /// Seconds this folder's images stay up, overriding the slideshow interval.
+    pub interval_secs: Option<u64>,
+}
+
+impl FolderSource {
+    /// Return true when `path` was discovered by scanning this folder.
+    pub fn contains(&self, path: &Path) -> bool {
+        if self.include_subfolders {
+            path.starts_with(&self.path)
+        } else {
+            path.parent()
+                .map(|parent| parent == self.path.as_path())
+                .unwrap_or(false)
+        }
+    }
 }
 
 /// Collect all matching images from folders and an optional single file.
@@ -80,6 +115,134 @@ pub fn collect_images(
     Ok(images)
 }
 
+/// Reorder an image list for sequential playback.
+pub fn sort_images(images: &mut Vec<PathBuf>, folders: &[FolderSource], mode: SortMode) {
+    match mode {
+        SortMode::Name => images.sort(),
+        SortMode::NaturalName => images.sort_by(|a, b| natural_cmp(a, b)),
+        SortMode::Modified => {
+            images.sort_by_cached_key(|path| (modified_time(path), path.clone()));
+        }
+        SortMode::ExifDate => {
+            // Images without EXIF dates keep their mtime order after the dated ones.
+            images.sort_by_cached_key(|path| {
+                let taken = exif_date_taken(path);
+                (taken.is_none(), taken, modified_time(path), path.clone())
+            });
+        }
+        SortMode::Size => {
+            images.sort_by_cached_key(|path| {
+                let size = std::fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+                (size, path.clone())
+            });
+        }
+        SortMode::FolderRoundRobin => {
+            images.sort_by(|a, b| natural_cmp(a, b));
+            *images = interleave_by_folder(std::mem::take(images), folders);
+        }
+    }
+}
+
+/// Compare paths so digit runs are ordered by numeric value.
+fn natural_cmp(a: &Path, b: &Path) -> Ordering {
+    let a = a.to_string_lossy().to_lowercase();
+    let b = b.to_string_lossy().to_lowercase();
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let a_num = take_digits(&mut a_chars);
+                let b_num = take_digits(&mut b_chars);
+                // Compare by length first so arbitrarily long runs never overflow.
+                let a_trim = a_num.trim_start_matches('0');
+                let b_trim = b_num.trim_start_matches('0');
+                let ord = a_trim
+                    .len()
+                    .cmp(&b_trim.len())
+                    .then_with(|| a_trim.cmp(b_trim))
+                    .then_with(|| a_num.len().cmp(&b_num.len()));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(ca), Some(cb)) => {
+                if ca != cb {
+                    return ca.cmp(&cb);
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+/// Consume a run of ASCII digits from the iterator.
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().copied() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+/// Read a file's modification time, treating unreadable files as oldest.
+fn modified_time(path: &Path) -> SystemTime {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Read EXIF `DateTimeOriginal` as its raw `YYYY:MM:DD HH:MM:SS` text.
+fn exif_date_taken(path: &Path) -> Option<Vec<u8>> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+    match &field.value {
+        // The fixed-width EXIF format sorts chronologically as plain bytes.
+        exif::Value::Ascii(values) => values
+            .first()
+            .filter(|value| exif::DateTime::from_ascii(value).is_ok())
+            .cloned(),
+        _ => None,
+    }
+}
+
+/// Interleave images so consecutive entries come from different source folders.
+fn interleave_by_folder(images: Vec<PathBuf>, folders: &[FolderSource]) -> Vec<PathBuf> {
+    // One bucket per folder plus a trailing bucket for the single-image source.
+    let mut buckets: Vec<Vec<PathBuf>> = vec![Vec::new(); folders.len() + 1];
+    for path in images {
+        let idx = folders
+            .iter()
+            .position(|folder| folder.contains(&path))
+            .unwrap_or(folders.len());
+        buckets[idx].push(path);
+    }
+
+    let total = buckets.iter().map(Vec::len).sum();
+    let mut iters: Vec<_> = buckets.into_iter().map(Vec::into_iter).collect();
+    let mut result = Vec::with_capacity(total);
+    while result.len() < total {
+        for iter in iters.iter_mut() {
+            if let Some(path) = iter.next() {
+                result.push(path);
+            }
+        }
+    }
+    result
+}
+
 /// Return true when the file extension is a supported image type.
 pub fn is_supported_image(path: &Path) -> bool {
     match path.extension().and_then(OsStr::to_str) {
@@ -91,42 +254,86 @@ pub fn is_supported_image(path: &Path) -> bool {
     }
 }
 
-/// Pick a random image, avoiding the previous image when possible.
-pub fn pick_random(images: &[PathBuf], last: Option<&PathBuf>) -> Result<PathBuf> {
-    let mut rng = rand::thread_rng();
-    if images.len() == 1 {
-        return Ok(images[0].clone());
-    }
-    for _ in 0..5 {
-        let candidate = images
-            .choose(&mut rng)
-            .ok_or_else(|| anyhow!("no images available"))?;
-        if Some(candidate) != last {
-            return Ok(candidate.clone());
-        }
-    }
-    Ok(images
-        .choose(&mut rng)
-        .ok_or_else(|| anyhow!("no images available"))?
-        .clone())
-}
-
 /// Load, optionally rotate, and cache an image as a BMP for Windows.
-pub fn process_image(path: &Path, auto_rotate: bool) -> Result<PathBuf> {
-    let mut img =
-        image::open(path).with_context(|| format!("failed to open {}", path.display()))?;
-    if auto_rotate && img.width() < img.height() {
-        img = img.rotate90();
+///
+/// `auto_rotate` turns portrait images to landscape in the given direction, and
+/// `render` pre-renders the frame the desktop will show.
+pub fn process_image(
+    path: &Path,
+    auto_rotate: Option<RotateDirection>,
+    render: Option<RenderTarget>,
+) -> Result<PathBuf> {
+    let mut img = open_image(path)?;
+    let mut focus = focus::lookup(path);
+    if let Some(direction) = auto_rotate
+        && img.width() < img.height()
+    {
+        img = rotate_quarter(&img, direction);
+        focus = focus.map(|point| rotate_focus(point, direction));
     }
 
+    write_cache(&img, render, focus)
+}
+
+/// Save the wallpaper BMP, pre-rendering it to `render` when set.
+fn write_cache(
+    img: &DynamicImage,
+    render: Option<RenderTarget>,
+    focus: Option<FocusPoint>,
+) -> Result<PathBuf> {
+    let rgb = match render {
+        Some(target) => render_frame(img, &target, focus),
+        None => img.to_rgb8(),
+    };
     let cache_path = cache_file_path()?;
     // Windows wallpaper APIs are most reliable with BMP input.
-    let rgb = img.to_rgb8();
     rgb.save_with_format(&cache_path, ImageFormat::Bmp)
         .with_context(|| format!("failed to write {}", cache_path.display()))?;
     Ok(cache_path)
 }
 
+/// Upright copy of an image that fits in a `max_side` square, for on-screen previews.
+pub fn load_thumbnail(path: &Path, max_side: u32) -> Result<RgbImage> {
+    Ok(open_image(path)?.thumbnail(max_side, max_side).to_rgb8())
+}
+
+/// Decode an image and apply its EXIF orientation, so pixels are upright.
+fn open_image(path: &Path) -> Result<DynamicImage> {
+    let mut img =
+        image::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    if let Some(orientation) = exif_orientation(path) {
+        img.apply_orientation(orientation);
+    }
+    Ok(img)
+}
+
+/// Read the EXIF `Orientation` tag; `None` when missing or invalid.
+fn exif_orientation(path: &Path) -> Option<Orientation> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let field = exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?;
+    let value = field.value.get_uint(0)?;
+    Orientation::from_exif(u8::try_from(value).ok()?)
+}
+
+/// Where a focal point ends up after [`rotate_quarter`].
+fn rotate_focus(point: FocusPoint, direction: RotateDirection) -> FocusPoint {
+    match direction {
+        RotateDirection::Clockwise => FocusPoint::new(1.0 - point.y, point.x),
+        RotateDirection::CounterClockwise => FocusPoint::new(point.y, 1.0 - point.x),
+    }
+}
+
+/// Turn an image a quarter in `direction`.
+fn rotate_quarter(img: &DynamicImage, direction: RotateDirection) -> DynamicImage {
+    match direction {
+        RotateDirection::Clockwise => img.rotate90(),
+        RotateDirection::CounterClockwise => img.rotate270(),
+    }
+}
+
 /// Resolve the cached wallpaper path used for reapplying styles.
 pub fn cached_wallpaper_path() -> Result<PathBuf> {
     cache_file_path()
@@ -141,306 +348,640 @@ fn cache_file_path() -> Result<PathBuf> {
     Ok(cache_dir.join("current.bmp"))
 }
 
+/// Layout and framing for stitched wallpapers.
+#[derive(Debug, Clone)]
+pub struct StitchOptions {
+    /// Slots images are placed in, in order.
+    pub layout: StitchLayout,
+    /// Size the stitched canvas is scaled and cropped to; `None` keeps its natural size.
+    pub crop: Option<(u32, u32)>,
+    /// Which part of the canvas, or of each cell when covering, survives the crop.
+    pub crop_mode: CropMode,
+    /// How images are sized to their slots.
+    pub scaling: StitchScaling,
+    /// Gaps, margin and corners between the images.
+    pub style: StitchStyle,
+    /// Background for the parts of each cell an image leaves uncovered.
+    pub letterbox: Letterbox,
+}
+
 /// Stitch multiple images together into a single image.
 pub fn stitch_images(
     paths: &[PathBuf],
-    auto_rotate: bool,
-    orientation: StitchOrientation,
-    crop_enabled: bool,
-    crop_width: u32,
-    crop_height: u32,
+    auto_rotate: Option<RotateDirection>,
+    options: &StitchOptions,
+    render: Option<RenderTarget>,
 ) -> Result<PathBuf> {
     if paths.is_empty() {
         return Err(anyhow!("no images to stitch"));
     }
     if paths.len() == 1 {
-        return process_image(&paths[0], auto_rotate);
+        return process_image(&paths[0], auto_rotate, render);
     }
 
     // Load all images
+    let focus_map = focus::shared();
     let mut images: Vec<DynamicImage> = Vec::with_capacity(paths.len());
+    let mut focus: Vec<Option<FocusPoint>> = Vec::with_capacity(paths.len());
     for path in paths {
-        let img =
-            image::open(path).with_context(|| format!("failed to open {}", path.display()))?;
-        images.push(img);
+        images.push(open_image(path)?);
+        focus.push(focus_map.point(path));
     }
 
-    // Apply smart rotation based on orientation and count
-    let rotated_images = apply_smart_rotation(&images, orientation, auto_rotate);
+    // Turn each image to suit its slot
+    let rotated_images = apply_smart_rotation(&images, &mut focus, &options.layout, auto_rotate);
 
-    // Stitch based on layout
-    let stitched = stitch_with_layout(&rotated_images, orientation);
-
-    let final_image = if crop_enabled {
-        crop_center(&stitched, crop_width, crop_height)
+    // The margin comes out of the output size; the images fill what is left.
+    let style = match options.crop {
+        Some((width, height)) => options.style.fitted(width, height),
+        None => options.style,
+    };
+    let target = options
+        .crop
+        .map(|(width, height)| style.inner_size(width, height));
+    let composite = stitch_with_layout(&rotated_images, &focus, options, target);
+
+    let (image, window) = match target {
+        Some((width, height)) if composite.image.dimensions() != (width, height) => {
+            crop_to_fill_mapped(
+                &composite.image,
+                width,
+                height,
+                options.crop_mode,
+                canvas_focus(&composite.image, &composite.placements, &focus),
+                FilterType::Lanczos3,
+            )
+        }
+        _ => (composite.image, CropWindow::IDENTITY),
+    };
+    let final_image = if style.is_plain() {
+        image
     } else {
-        stitched
+        let cells: Vec<_> = composite
+            .cells
+            .iter()
+            .filter_map(|cell| cell.mapped(&window, image.width(), image.height()))
+            .collect();
+        decorate(&image, &cells, &style)
     };
 
-    let cache_path = cache_file_path()?;
-    final_image
-        .save_with_format(&cache_path, ImageFormat::Bmp)
-        .with_context(|| format!("failed to write {}", cache_path.display()))?;
-    Ok(cache_path)
+    // The canvas crop already honoured the focal points.
+    write_cache(&DynamicImage::ImageRgb8(final_image), render, None)
+}
+
+/// A stitched canvas and where its images went.
+struct Composite {
+    /// The stitched canvas.
+    image: RgbImage,
+    /// Where each image's pixels landed, in input order.
+    placements: Vec<Rect>,
+    /// Slot each image was given, in input order.
+    cells: Vec<Rect>,
+}
+
+/// Pixel rectangle on a stitched canvas.
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    /// Left edge on the canvas.
+    x: i64,
+    /// Top edge on the canvas.
+    y: i64,
+    /// Width in pixels.
+    width: u32,
+    /// Height in pixels.
+    height: u32,
+}
+
+impl Rect {
+    /// Rectangle from the `(x, y, width, height)` form used by layout frames.
+    fn from_pixels((x, y, width, height): (u32, u32, u32, u32)) -> Self {
+        Self {
+            x: x as i64,
+            y: y as i64,
+            width,
+            height,
+        }
+    }
+
+    /// Where the rectangle lands on a `width` x `height` frame cut by `window`,
+    /// clipped to the frame; `None` when nothing of it is left.
+    fn mapped(&self, window: &CropWindow, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+        let (left, top) = window.map(self.x as f64, self.y as f64);
+        let (right, bottom) = window.map(
+            (self.x + self.width as i64) as f64,
+            (self.y + self.height as i64) as f64,
+        );
+        let clip = |at: f64, size: u32| at.round().clamp(0.0, size as f64) as u32;
+        let (left, right) = (clip(left, width), clip(right, width));
+        let (top, bottom) = (clip(top, height), clip(bottom, height));
+        (right > left && bottom > top).then(|| (left, top, right - left, bottom - top))
+    }
+
+    /// `img` centred in a `cell_width` x `cell_height` cell at (`x`, `y`), as
+    /// [`letterbox`] places it.
+    fn centered(x: i64, y: i64, cell_width: u32, cell_height: u32, img: &DynamicImage) -> Self {
+        Self {
+            x: x + (cell_width as i64 - img.width() as i64) / 2,
+            y: y + (cell_height as i64 - img.height() as i64) / 2,
+            width: img.width(),
+            height: img.height(),
+        }
+    }
 }
 
-/// Target orientation for each image slot.
-#[derive(Clone, Copy, PartialEq)]
-enum TargetOrientation {
-    /// Portrait: width < height
-    Vertical,
-    /// Landscape: width > height
-    Horizontal,
+/// Single focal point for a stitched canvas: the middle of the box around every
+/// image's focal point, so as many as possible stay in frame.
+fn canvas_focus(
+    canvas: &RgbImage,
+    placements: &[Rect],
+    focus: &[Option<FocusPoint>],
+) -> Option<FocusPoint> {
+    if canvas.width() == 0 || canvas.height() == 0 {
+        return None;
+    }
+    let points: Vec<(f32, f32)> = placements
+        .iter()
+        .zip(focus)
+        .filter_map(|(placement, point)| {
+            let point = (*point)?;
+            Some((
+                placement.x as f32 + point.x * placement.width as f32,
+                placement.y as f32 + point.y * placement.height as f32,
+            ))
+        })
+        .collect();
+    if points.is_empty() {
+        return None;
+    }
+    let span = |values: Vec<f32>| {
+        let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        (min + max) / 2.0
+    };
+    let x = span(points.iter().map(|point| point.0).collect());
+    let y = span(points.iter().map(|point| point.1).collect());
+    Some(FocusPoint::new(
+        x / canvas.width() as f32,
+        y / canvas.height() as f32,
+    ))
 }
 
-/// Apply smart rotation to images based on stitch orientation and count.
+/// Apply smart rotation to images so each matches its layout slot.
+///
+/// Focal points are turned along with their images.
 fn apply_smart_rotation(
     images: &[DynamicImage],
-    orientation: StitchOrientation,
-    auto_rotate: bool,
+    focus: &mut [Option<FocusPoint>],
+    layout: &StitchLayout,
+    auto_rotate: Option<RotateDirection>,
 ) -> Vec<DynamicImage> {
-    if !auto_rotate {
+    let Some(direction) = auto_rotate else {
         return images.to_vec();
-    }
-
-    let count = images.len();
-    let targets = get_rotation_pattern(orientation, count);
+    };
 
+    let mut slots = layout.slots();
     images
         .iter()
-        .enumerate()
-        .map(|(i, img)| {
-            let target = targets
-                .get(i)
-                .copied()
-                .unwrap_or(TargetOrientation::Vertical);
-            rotate_to_target(img, target)
+        .zip(focus.iter_mut())
+        .map(|(img, point)| {
+            let target = slots
+                .next()
+                .map_or(SlotOrientation::Any, |slot| slot.orientation);
+            if needs_rotation(img, target) {
+                *point = point.map(|point| rotate_focus(point, direction));
+                rotate_quarter(img, direction)
+            } else {
+                img.clone()
+            }
         })
         .collect()
 }
 
-/// Get the rotation pattern based on orientation and image count.
-fn get_rotation_pattern(orientation: StitchOrientation, count: usize) -> Vec<TargetOrientation> {
-    use TargetOrientation::{Horizontal as H, Vertical as V};
-
-    match orientation {
-        StitchOrientation::Horizontal => match count {
-            2 => vec![V, V],
-            3 => vec![V, H, V],
-            4 => vec![H, H, H, H],    // 2x2 grid of H
-            5 => vec![V, V, V, H, H], // 3V top + 2H bottom
-            _ => vec![V; count],
-        },
-        StitchOrientation::Vertical => match count {
-            2 => vec![H, H],
-            3 => vec![H, H, H],
-            4 => vec![V, V, V, V],    // 2x2 grid of V
-            5 => vec![H, H, H, V, V], // 3H top + 2V bottom
-            _ => vec![H; count],
-        },
-    }
-}
-
-/// Rotate image to match target orientation.
-fn rotate_to_target(img: &DynamicImage, target: TargetOrientation) -> DynamicImage {
+/// Return true when an image must be turned to match the slot orientation.
+fn needs_rotation(img: &DynamicImage, target: SlotOrientation) -> bool {
     let is_portrait = img.width() < img.height();
-    let needs_portrait = target == TargetOrientation::Vertical;
-
-    if is_portrait != needs_portrait {
-        img.rotate90()
-    } else {
-        img.clone()
+    match target {
+        SlotOrientation::Any => false,
+        SlotOrientation::Portrait => !is_portrait,
+        SlotOrientation::Landscape => is_portrait,
     }
 }
 
-/// Stitch images using the appropriate layout.
-fn stitch_with_layout(images: &[DynamicImage], orientation: StitchOrientation) -> RgbImage {
-    let count = images.len();
-
-    match orientation {
-        StitchOrientation::Horizontal => match count {
-            4 => stitch_grid(images, 2, 2),     // 2x2 grid
-            5 => stitch_two_rows(images, 3, 2), // 3 top + 2 bottom
-            _ => stitch_horizontal(images),
-        },
-        StitchOrientation::Vertical => match count {
-            4 => stitch_grid(images, 2, 2),     // 2x2 grid
-            5 => stitch_two_rows(images, 3, 2), // 3 top + 2 bottom
-            _ => stitch_vertical(images),
-        },
+/// Stitch images into the slots of `options.layout`, in reading order, scaled as
+/// `options.scaling` says; `target` is the size the canvas will be cropped to.
+fn stitch_with_layout(
+    images: &[DynamicImage],
+    focus: &[Option<FocusPoint>],
+    options: &StitchOptions,
+    target: Option<(u32, u32)>,
+) -> Composite {
+    match options.scaling {
+        StitchScaling::Native => stitch_native(images, &options.layout, options.letterbox),
+        StitchScaling::Normalized => stitch_normalized(images, &options.layout, target),
+        StitchScaling::Cover => stitch_cover(images, focus, options, target),
     }
 }
 
-/// Stitch images in a grid layout (rows x cols).
-fn stitch_grid(images: &[DynamicImage], rows: usize, cols: usize) -> RgbImage {
-    // Calculate cell dimensions (use max dimensions per cell)
-    let mut row_heights = vec![0u32; rows];
-    let mut col_widths = vec![0u32; cols];
-
-    for (i, img) in images.iter().enumerate() {
-        let row = i / cols;
-        let col = i % cols;
-        if row < rows {
-            row_heights[row] = row_heights[row].max(img.height());
-            col_widths[col] = col_widths[col].max(img.width());
-        }
-    }
-
-    let total_width: u32 = col_widths.iter().sum();
-    let total_height: u32 = row_heights.iter().sum();
-
-    let mut result = RgbImage::new(total_width, total_height);
+/// Smallest canvas on which every image fits its weighted slot at native size.
+fn native_canvas_size(images: &[DynamicImage], frames: &[Frame]) -> (u32, u32) {
+    let needed = |size: u32, share: f64| (size as f64 / share.max(f64::EPSILON)).ceil();
+    let width = images
+        .iter()
+        .zip(frames)
+        .map(|(img, frame)| needed(img.width(), frame.width))
+        .fold(0.0, f64::max);
+    let height = images
+        .iter()
+        .zip(frames)
+        .map(|(img, frame)| needed(img.height(), frame.height))
+        .fold(0.0, f64::max);
+    (width as u32, height as u32)
+}
 
-    for (i, img) in images.iter().enumerate() {
-        let row = i / cols;
-        let col = i % cols;
-        if row >= rows {
-            break;
+/// Canvas size and cells of images laid end to end at native size along each
+/// line, as unweighted layouts place them: a line is as thick as its thickest
+/// image, and lines shorter than the longest are centred.
+///
+/// When every line has the same number of slots the layout is a grid, and each
+/// slot position is as long as its longest image so cells line up across lines.
+fn native_line_cells(images: &[DynamicImage], layout: &StitchLayout) -> (u32, u32, Vec<Rect>) {
+    let rows = layout.direction == LayoutDirection::Rows;
+    // Size of an image along its line, then across it.
+    let extent = |img: &DynamicImage| {
+        if rows {
+            (img.width(), img.height())
+        } else {
+            (img.height(), img.width())
         }
-
-        let x_offset: u32 = col_widths[..col].iter().sum();
-        let y_offset: u32 = row_heights[..row].iter().sum();
-
-        // Center image in its cell
-        let cell_width = col_widths[col];
-        let cell_height = row_heights[row];
-        let img_x = x_offset + (cell_width - img.width()) / 2;
-        let img_y = y_offset + (cell_height - img.height()) / 2;
-
-        let rgb = img.to_rgb8();
-        for (x, y, pixel) in rgb.enumerate_pixels() {
-            let px = img_x + x;
-            let py = img_y + y;
-            if px < total_width && py < total_height {
-                result.put_pixel(px, py, *pixel);
+    };
+    let lines = split_lines(images, layout);
+    let grid = layout
+        .lines
+        .windows(2)
+        .all(|pair| pair[0].slots.len() == pair[1].slots.len());
+    // Length of each slot position along a grid's lines.
+    let mut columns = Vec::new();
+    if grid {
+        for line in &lines {
+            for (idx, img) in line.iter().enumerate() {
+                if idx == columns.len() {
+                    columns.push(0);
+                }
+                columns[idx] = columns[idx].max(extent(img).0);
             }
         }
     }
-
-    result
+    let along_of = |idx: usize, img: &DynamicImage| {
+        if grid { columns[idx] } else { extent(img).0 }
+    };
+    let lengths: Vec<u32> = lines
+        .iter()
+        .map(|line| {
+            line.iter()
+                .enumerate()
+                .map(|(idx, img)| along_of(idx, img))
+                .sum()
+        })
+        .collect();
+    let length = lengths.iter().copied().max().unwrap_or(0);
+
+    let mut cells = Vec::with_capacity(images.len());
+    let mut across_at = 0;
+    for (line, line_length) in lines.iter().zip(&lengths) {
+        let thickness = line.iter().map(|img| extent(img).1).max().unwrap_or(0);
+        // Grid lines start at the edge so a partly filled last line keeps its columns.
+        let mut along_at = if grid { 0 } else { (length - line_length) / 2 };
+        for (idx, img) in line.iter().enumerate() {
+            let along = along_of(idx, img);
+            cells.push(Rect::from_pixels(if rows {
+                (along_at, across_at, along, thickness)
+            } else {
+                (across_at, along_at, thickness, along)
+            }));
+            along_at += along;
+        }
+        across_at += thickness;
+    }
+    if rows {
+        (length, across_at, cells)
+    } else {
+        (across_at, length, cells)
+    }
 }
 
-/// Stitch images in two rows with different counts.
-fn stitch_two_rows(images: &[DynamicImage], top_count: usize, bottom_count: usize) -> RgbImage {
-    let (top_images, bottom_images) = images.split_at(top_count.min(images.len()));
+/// Images grouped into the layout's lines, in reading order; lines left without
+/// images are dropped.
+fn split_lines<'a>(images: &'a [DynamicImage], layout: &StitchLayout) -> Vec<&'a [DynamicImage]> {
+    let mut remaining = images;
+    layout
+        .lines
+        .iter()
+        .map(|line| {
+            let (head, tail) = remaining.split_at(line.slots.len().min(remaining.len()));
+            remaining = tail;
+            head
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
 
-    let top_row = stitch_horizontal(top_images);
-    let bottom_row = if !bottom_images.is_empty() {
-        stitch_horizontal(&bottom_images[..bottom_count.min(bottom_images.len())])
+/// Place images at native size, centred in their cells on the smallest canvas
+/// that holds them; canvas areas left without an image keep the letterbox colour.
+///
+/// Unweighted layouts put images end to end along each line, as the built-in
+/// layouts always have; weighted ones size every slot by its share of the canvas.
+fn stitch_native(images: &[DynamicImage], layout: &StitchLayout, fill: Letterbox) -> Composite {
+    let (width, height, cells) = if layout.is_weighted() {
+        let frames = layout.frames();
+        let (width, height) = native_canvas_size(images, &frames);
+        let cells = frames
+            .iter()
+            .take(images.len())
+            .map(|frame| Rect::from_pixels(frame.pixels(width, height)))
+            .collect();
+        (width, height, cells)
     } else {
-        RgbImage::new(0, 0)
+        native_line_cells(images, layout)
     };
 
-    // Stack vertically, centering the narrower row
-    let total_width = top_row.width().max(bottom_row.width());
-    let total_height = top_row.height() + bottom_row.height();
-
-    let mut result = RgbImage::new(total_width, total_height);
-
-    // Top row centered
-    let top_x = (total_width - top_row.width()) / 2;
-    for (x, y, pixel) in top_row.enumerate_pixels() {
-        result.put_pixel(top_x + x, y, *pixel);
+    let mut result = RgbImage::from_pixel(width, height, Rgb(fill.color));
+    let mut placements = Vec::with_capacity(cells.len());
+    for (img, cell) in images.iter().zip(&cells) {
+        let framed = letterbox(&img.to_rgb8(), cell.width, cell.height, fill);
+        imageops::replace(&mut result, &framed, cell.x, cell.y);
+        placements.push(Rect::centered(cell.x, cell.y, cell.width, cell.height, img));
     }
 
-    // Bottom row centered
-    let bottom_x = (total_width - bottom_row.width()) / 2;
-    let bottom_y = top_row.height();
-    for (x, y, pixel) in bottom_row.enumerate_pixels() {
-        result.put_pixel(bottom_x + x, bottom_y + y, *pixel);
+    Composite {
+        image: result,
+        placements,
+        cells,
     }
-
-    result
 }
 
-/// Stitch images horizontally (side by side).
-fn stitch_horizontal(images: &[DynamicImage]) -> RgbImage {
-    let max_height = images.iter().map(|img| img.height()).max().unwrap_or(0);
-    let total_width: u32 = images.iter().map(|img| img.width()).sum();
+/// Scale each line's images to a common thickness so they meet edge to edge, and
+/// every line to the same length, leaving no gaps.
+///
+/// Lines are as long as `target` along the layout direction (the longest native
+/// line without one); slot and line weights do not apply.
+fn stitch_normalized(
+    images: &[DynamicImage],
+    layout: &StitchLayout,
+    target: Option<(u32, u32)>,
+) -> Composite {
+    let rows = layout.direction == LayoutDirection::Rows;
+    // Length of an image along its line when its thickness is 1.
+    let ratio = |img: &DynamicImage| {
+        let (along, across) = if rows {
+            (img.width(), img.height())
+        } else {
+            (img.height(), img.width())
+        };
+        along as f64 / across.max(1) as f64
+    };
 
-    let mut result = RgbImage::new(total_width, max_height);
+    let lines = split_lines(images, layout);
+
+    let length = match target {
+        Some((width, height)) => (if rows { width } else { height }) as f64,
+        None => lines
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|img| if rows { img.width() } else { img.height() } as f64)
+                    .sum::<f64>()
+            })
+            .fold(0.0, f64::max),
+    };
+    let thicknesses: Vec<u32> = lines
+        .iter()
+        .map(|line| {
+            let total: f64 = line.iter().map(ratio).sum();
+            (length / total.max(f64::EPSILON)).round().max(1.0) as u32
+        })
+        .collect();
+    let length = length.round() as u32;
+    let thickness: u32 = thicknesses.iter().sum();
+    let (width, height) = if rows {
+        (length, thickness)
+    } else {
+        (thickness, length)
+    };
 
-    let mut x_offset = 0u32;
-    for img in images {
-        let rgb = img.to_rgb8();
-        let y_offset = (max_height - img.height()) / 2;
-        for (x, y, pixel) in rgb.enumerate_pixels() {
-            if x_offset + x < total_width && y_offset + y < max_height {
-                result.put_pixel(x_offset + x, y_offset + y, *pixel);
-            }
+    let mut result = RgbImage::new(width, height);
+    let mut placements = Vec::with_capacity(images.len());
+    let mut line_start = 0;
+    for (line, &line_thickness) in lines.iter().zip(&thicknesses) {
+        let total: f64 = line.iter().map(ratio).sum();
+        let mut along = 0.0;
+        for img in line.iter() {
+            // Edges are rounded rather than sizes, so the line ends exactly at `length`.
+            let start = (along / total * length as f64).round() as u32;
+            along += ratio(img);
+            let end = (along / total * length as f64).round() as u32;
+            let (x, y, cell_width, cell_height) = if rows {
+                (start, line_start, end - start, line_thickness)
+            } else {
+                (line_start, start, line_thickness, end - start)
+            };
+            let scaled = imageops::resize(
+                &img.to_rgb8(),
+                cell_width.max(1),
+                cell_height.max(1),
+                FilterType::Lanczos3,
+            );
+            imageops::replace(&mut result, &scaled, x as i64, y as i64);
+            placements.push(Rect::from_pixels((x, y, cell_width, cell_height)));
         }
-        x_offset += img.width();
+        line_start += line_thickness;
     }
 
-    result
+    // Images fill their cells exactly.
+    Composite {
+        image: result,
+        cells: placements.clone(),
+        placements,
+    }
 }
 
-/// Stitch images vertically (top to bottom).
-fn stitch_vertical(images: &[DynamicImage]) -> RgbImage {
-    let max_width = images.iter().map(|img| img.width()).max().unwrap_or(0);
-    let total_height: u32 = images.iter().map(|img| img.height()).sum();
-
-    let mut result = RgbImage::new(max_width, total_height);
-
-    let mut y_offset = 0u32;
-    for img in images {
-        let rgb = img.to_rgb8();
-        let x_offset = (max_width - img.width()) / 2;
-        for (x, y, pixel) in rgb.enumerate_pixels() {
-            if x_offset + x < max_width && y_offset + y < total_height {
-                result.put_pixel(x_offset + x, y_offset + y, *pixel);
-            }
-        }
-        y_offset += img.height();
+/// Crop each image to fill its slot, on a canvas of the `target` size (the native
+/// canvas size without one); focal points and the crop mode pick what each cell
+/// keeps.
+fn stitch_cover(
+    images: &[DynamicImage],
+    focus: &[Option<FocusPoint>],
+    options: &StitchOptions,
+    target: Option<(u32, u32)>,
+) -> Composite {
+    let frames = options.layout.frames();
+    let (width, height) = target.unwrap_or_else(|| native_canvas_size(images, &frames));
+
+    let mut result = RgbImage::from_pixel(width, height, Rgb(options.letterbox.color));
+    let mut placements = Vec::with_capacity(images.len());
+    for ((img, frame), point) in images.iter().zip(&frames).zip(focus) {
+        let (x, y, cell_width, cell_height) = frame.pixels(width, height);
+        let cell = crop_to_fill(
+            &img.to_rgb8(),
+            cell_width,
+            cell_height,
+            options.crop_mode,
+            *point,
+            FilterType::Lanczos3,
+        );
+        imageops::replace(&mut result, &cell, x as i64, y as i64);
+        placements.push(Rect::from_pixels((x, y, cell_width, cell_height)));
     }
 
-    result
+    Composite {
+        image: result,
+        cells: placements.clone(),
+        placements,
+    }
 }
 
-/// Scale and crop an image to exactly fill the target dimensions.
-/// First scales the image so one dimension matches the target (cover fit),
-/// then crops the excess from the center.
-fn crop_center(img: &RgbImage, target_width: u32, target_height: u32) -> RgbImage {
-    let src_width = img.width();
-    let src_height = img.height();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{LetterboxFill, StitchOrientation};
+
+    fn sorted(names: &[&str]) -> Vec<String> {
+        let mut paths: Vec<PathBuf> = names.iter().map(PathBuf::from).collect();
+        paths.sort_by(|a, b| natural_cmp(a, b));
+        paths
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn natural_cmp_orders_digit_runs_by_value() {
+        assert_eq!(
+            sorted(&["img10.jpg", "img2.jpg", "img1.jpg"]),
+            ["img1.jpg", "img2.jpg", "img10.jpg"]
+        );
+    }
 
-    if src_width == 0 || src_height == 0 || target_width == 0 || target_height == 0 {
-        return img.clone();
+    #[test]
+    fn natural_cmp_breaks_leading_zero_ties_by_length() {
+        assert_eq!(
+            natural_cmp(Path::new("a007"), Path::new("a7")),
+            Ordering::Greater
+        );
+        assert_eq!(
+            natural_cmp(Path::new("a007"), Path::new("a8")),
+            Ordering::Less
+        );
+        assert_eq!(sorted(&["a10", "a010", "a9"]), ["a9", "a10", "a010"]);
     }
 
-    // Calculate scale factors for both dimensions
-    let scale_x = target_width as f64 / src_width as f64;
-    let scale_y = target_height as f64 / src_height as f64;
+    #[test]
+    fn natural_cmp_ignores_case() {
+        assert_eq!(
+            natural_cmp(Path::new("Beach2"), Path::new("beach2")),
+            Ordering::Equal
+        );
+        assert_eq!(
+            sorted(&["b.jpg", "A.jpg", "a2.jpg"]),
+            ["A.jpg", "a2.jpg", "b.jpg"]
+        );
+    }
 
-    // Use the larger scale factor to ensure the image covers the target area
-    let scale = scale_x.max(scale_y);
+    #[test]
+    fn natural_cmp_handles_runs_longer_than_u64() {
+        let long = "pic123456789012345678901234567890";
+        let longer = "pic1234567890123456789012345678901";
+        assert_eq!(
+            natural_cmp(Path::new(long), Path::new(longer)),
+            Ordering::Less
+        );
+        assert_eq!(
+            natural_cmp(Path::new(longer), Path::new("pic99")),
+            Ordering::Greater
+        );
+        assert_eq!(
+            natural_cmp(Path::new(long), Path::new(long)),
+            Ordering::Equal
+        );
+    }
 
-    let scaled_width = (src_width as f64 * scale).round() as u32;
-    let scaled_height = (src_height as f64 * scale).round() as u32;
+    fn blank(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+    }
 
-    // Scale the image using the image crate
-    let dynamic_img = DynamicImage::ImageRgb8(img.clone());
-    let scaled = dynamic_img.resize_exact(
-        scaled_width,
-        scaled_height,
-        image::imageops::FilterType::Lanczos3,
-    );
-    let scaled_rgb = scaled.to_rgb8();
+    fn black() -> Letterbox {
+        Letterbox {
+            fill: LetterboxFill::Solid,
+            color: [0, 0, 0],
+        }
+    }
 
-    // Crop from center to target dimensions
-    let crop_x = (scaled_width.saturating_sub(target_width)) / 2;
-    let crop_y = (scaled_height.saturating_sub(target_height)) / 2;
+    #[test]
+    fn native_stitch_sums_sizes_along_automatic_lines() {
+        let images = [blank(100, 80), blank(300, 60)];
+        let rows = StitchLayout::automatic(StitchOrientation::Horizontal, 2);
+        let stitched = stitch_native(&images, &rows, black());
+        assert_eq!(stitched.image.dimensions(), (400, 80));
+        let columns = StitchLayout::automatic(StitchOrientation::Vertical, 2);
+        let stitched = stitch_native(&images, &columns, black());
+        assert_eq!(stitched.image.dimensions(), (300, 140));
+    }
 
-    let final_width = target_width.min(scaled_width);
-    let final_height = target_height.min(scaled_height);
+    #[test]
+    fn native_stitch_keeps_grid_columns_aligned() {
+        // Baseline 2x2 grid: columns as wide as their widest image, rows as tall
+        // as their tallest, images centred in their cells.
+        let images = [
+            blank(100, 80),
+            blank(300, 60),
+            blank(200, 50),
+            blank(120, 90),
+        ];
+        let grid = StitchLayout::automatic(StitchOrientation::Horizontal, 4);
+        let stitched = stitch_native(&images, &grid, black());
+        assert_eq!(stitched.image.dimensions(), (500, 170));
+        let cells: Vec<_> = stitched
+            .cells
+            .iter()
+            .map(|cell| (cell.x, cell.y, cell.width, cell.height))
+            .collect();
+        assert_eq!(
+            cells,
+            [
+                (0, 0, 200, 80),
+                (200, 0, 300, 80),
+                (0, 80, 200, 90),
+                (200, 80, 300, 90)
+            ]
+        );
+        let placed: Vec<_> = stitched
+            .placements
+            .iter()
+            .map(|rect| (rect.x, rect.y))
+            .collect();
+        assert_eq!(placed, [(50, 0), (200, 10), (0, 100), (290, 80)]);
+    }
 
-    let mut result = RgbImage::new(final_width, final_height);
-    for y in 0..final_height {
-        for x in 0..final_width {
-            let pixel = scaled_rgb.get_pixel(crop_x + x, crop_y + y);
-            result.put_pixel(x, y, *pixel);
-        }
+    #[test]
+    fn native_stitch_centres_shorter_lines() {
+        let images = [
+            blank(100, 50),
+            blank(100, 50),
+            blank(100, 50),
+            blank(60, 40),
+        ];
+        let layout = StitchLayout::parse("3 + 1", "rows / A A A / A").unwrap();
+        let stitched = stitch_native(&images, &layout, black());
+        assert_eq!(stitched.image.dimensions(), (300, 90));
+        let last = stitched.cells[3];
+        assert_eq!((last.x, last.y, last.width, last.height), (120, 50, 60, 40));
     }
 
-    result
+    #[test]
+    fn native_stitch_sizes_weighted_slots_by_share() {
+        let images = [blank(100, 80), blank(300, 80)];
+        let layout = StitchLayout::parse("wide first", "rows / 2A A").unwrap();
+        let stitched = stitch_native(&images, &layout, black());
+        // The second image needs its third of the width to hold 300 pixels.
+        assert_eq!(stitched.image.dimensions(), (900, 80));
+    }
 }
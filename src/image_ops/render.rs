@@ -0,0 +1,261 @@
+//! Pre-rendering of wallpaper styles to an exact output size.
+//!
+//! Mirrors what the desktop does for each [`StyleMode`], so the saved frame can be
+//! shown pixel for pixel on any backend.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgb, RgbImage};
+
+use super::crop::crop_to_fill;
+use crate::focus::FocusPoint;
+use crate::settings::{CropMode, LetterboxFill, ResampleFilter};
+use crate::wallpaper::StyleMode;
+
+/// Downscale factor applied before blurring a letterbox background.
+const BLUR_DOWNSCALE: u32 = 16;
+/// Blur radius (sigma) on the downscaled background.
+const BLUR_SIGMA: f32 = 3.0;
+/// Brightness kept in a blurred background, so it reads as a backdrop.
+const BLUR_BRIGHTNESS: f32 = 0.55;
+/// Bits kept per channel when grouping colours for the dominant colour.
+const DOMINANT_BITS: u32 = 3;
+
+/// What fills the area an image does not cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Letterbox {
+    /// Fill strategy.
+    pub fill: LetterboxFill,
+    /// Colour used by [`LetterboxFill::Solid`].
+    pub color: [u8; 3],
+}
+
+/// Output frame for a pre-rendered wallpaper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderTarget {
+    /// Frame width in pixels.
+    pub width: u32,
+    /// Frame height in pixels.
+    pub height: u32,
+    /// How the image is placed in the frame.
+    pub style: StyleMode,
+    /// Filter used whenever the image is scaled.
+    pub filter: ResampleFilter,
+    /// Background around Fit and Center images.
+    pub letterbox: Letterbox,
+    /// Part of the image Fill and Span keep.
+    pub crop: CropMode,
+}
+
+/// Render `img` into a frame of exactly `target`'s size.
+///
+/// Fit and Center fill the uncovered area with the target's letterbox; partial
+/// tiles always cover the frame. Fill and Span keep `focus` in frame.
+pub fn render_frame(
+    img: &DynamicImage,
+    target: &RenderTarget,
+    focus: Option<FocusPoint>,
+) -> RgbImage {
+    let (width, height) = (target.width.max(1), target.height.max(1));
+    let filter = filter_type(target.filter);
+    match target.style {
+        // Span covers the whole frame the same way; the frame is the virtual desktop.
+        StyleMode::Fill | StyleMode::Span => {
+            crop_to_fill(&img.to_rgb8(), width, height, target.crop, focus, filter)
+        }
+        StyleMode::Stretch => img.resize_exact(width, height, filter).to_rgb8(),
+        StyleMode::Fit => {
+            let scaled = img.resize(width, height, filter).to_rgb8();
+            letterbox(&scaled, width, height, target.letterbox)
+        }
+        StyleMode::Center => letterbox(&img.to_rgb8(), width, height, target.letterbox),
+        StyleMode::Tile => {
+            let tile = img.to_rgb8();
+            let mut frame = RgbImage::new(width, height);
+            // The desktop tiles from the top-left corner at the image's own size.
+            for y in (0..height).step_by(tile.height().max(1) as usize) {
+                for x in (0..width).step_by(tile.width().max(1) as usize) {
+                    imageops::replace(&mut frame, &tile, x as i64, y as i64);
+                }
+            }
+            frame
+        }
+    }
+}
+
+/// Place `img` in the middle of a `width` x `height` frame, cropping whatever
+/// overflows and filling the rest as `letterbox` says.
+pub fn letterbox(img: &RgbImage, width: u32, height: u32, letterbox: Letterbox) -> RgbImage {
+    let x = (width as i64 - img.width() as i64) / 2;
+    let y = (height as i64 - img.height() as i64) / 2;
+    let covered = x <= 0 && y <= 0;
+    let mut frame = if covered || img.width() == 0 || img.height() == 0 {
+        RgbImage::from_pixel(width, height, Rgb(letterbox.color))
+    } else {
+        match letterbox.fill {
+            LetterboxFill::Solid => RgbImage::from_pixel(width, height, Rgb(letterbox.color)),
+            LetterboxFill::Blur => blurred_backdrop(img, width, height),
+            LetterboxFill::Dominant => RgbImage::from_pixel(width, height, dominant_color(img)),
+            LetterboxFill::Mirror => mirrored(img, width, height, x, y),
+        }
+    };
+    imageops::replace(&mut frame, img, x, y);
+    frame
+}
+
+/// Darkened, heavily blurred copy of `img` covering the frame.
+fn blurred_backdrop(img: &RgbImage, width: u32, height: u32) -> RgbImage {
+    let small_width = (width / BLUR_DOWNSCALE).max(1);
+    let small_height = (height / BLUR_DOWNSCALE).max(1);
+    // Blurring a small copy is far cheaper and looks the same once scaled back up.
+    let small = DynamicImage::ImageRgb8(img.clone()).resize_to_fill(
+        small_width,
+        small_height,
+        FilterType::Triangle,
+    );
+    let mut small = imageops::blur(&small.to_rgb8(), BLUR_SIGMA);
+    for pixel in small.pixels_mut() {
+        for channel in pixel.0.iter_mut() {
+            *channel = (*channel as f32 * BLUR_BRIGHTNESS) as u8;
+        }
+    }
+    imageops::resize(&small, width, height, FilterType::Triangle)
+}
+
+/// Most common colour of `img`, averaged within its colour group.
+fn dominant_color(img: &RgbImage) -> Rgb<u8> {
+    let sample = imageops::thumbnail(img, 64, 64);
+    let shift = 8 - DOMINANT_BITS;
+    let bucket = |pixel: &Rgb<u8>| {
+        let [r, g, b] = pixel.0;
+        (((r >> shift) as usize) << (2 * DOMINANT_BITS))
+            | (((g >> shift) as usize) << DOMINANT_BITS)
+            | (b >> shift) as usize
+    };
+    let mut counts = vec![0_u32; 1 << (3 * DOMINANT_BITS)];
+    for pixel in sample.pixels() {
+        counts[bucket(pixel)] += 1;
+    }
+    let Some(best) = (0..counts.len()).max_by_key(|&idx| counts[idx]) else {
+        return Rgb([0, 0, 0]);
+    };
+    let mut sum = [0_u64; 3];
+    for pixel in sample.pixels().filter(|pixel| bucket(pixel) == best) {
+        for (total, channel) in sum.iter_mut().zip(pixel.0) {
+            *total += channel as u64;
+        }
+    }
+    let count = counts[best].max(1) as u64;
+    Rgb(sum.map(|total| (total / count) as u8))
+}
+
+/// Frame filled by reflecting `img`, placed at (`x`, `y`), across its edges.
+fn mirrored(img: &RgbImage, width: u32, height: u32, x: i64, y: i64) -> RgbImage {
+    let reflect = |offset: i64, size: u32| {
+        let size = size as i64;
+        let folded = offset.rem_euclid(2 * size);
+        (if folded < size {
+            folded
+        } else {
+            2 * size - 1 - folded
+        }) as u32
+    };
+    RgbImage::from_fn(width, height, |fx, fy| {
+        *img.get_pixel(
+            reflect(fx as i64 - x, img.width()),
+            reflect(fy as i64 - y, img.height()),
+        )
+    })
+}
+
+/// Map the persisted filter choice onto the `image` crate's filter.
+pub fn filter_type(filter: ResampleFilter) -> FilterType {
+    match filter {
+        ResampleFilter::Nearest => FilterType::Nearest,
+        ResampleFilter::Triangle => FilterType::Triangle,
+        ResampleFilter::CatmullRom => FilterType::CatmullRom,
+        ResampleFilter::Lanczos3 => FilterType::Lanczos3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gradient image, so every crop and fill has some detail to work with.
+    fn source(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            Rgb([(x * 7) as u8, (y * 5) as u8, ((x + y) * 3) as u8])
+        }))
+    }
+
+    fn target(style: StyleMode, fill: LetterboxFill, crop: CropMode) -> RenderTarget {
+        RenderTarget {
+            width: 64,
+            height: 36,
+            style,
+            filter: ResampleFilter::Triangle,
+            letterbox: Letterbox {
+                fill,
+                color: [10, 20, 30],
+            },
+            crop,
+        }
+    }
+
+    #[test]
+    fn every_style_fills_the_exact_frame() {
+        // Wide, tall, smaller and larger than the frame.
+        let sources = [
+            source(50, 20),
+            source(20, 50),
+            source(200, 90),
+            source(90, 200),
+        ];
+        for style in StyleMode::ALL {
+            for fill in LetterboxFill::ALL {
+                for crop in CropMode::ALL {
+                    for img in &sources {
+                        let frame = render_frame(img, &target(style, fill, crop), None);
+                        assert_eq!(
+                            frame.dimensions(),
+                            (64, 36),
+                            "{style:?} {fill:?} {crop:?} from {:?}",
+                            (img.width(), img.height())
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_target_renders_one_pixel() {
+        let mut target = target(StyleMode::Fill, LetterboxFill::Solid, CropMode::Center);
+        target.width = 0;
+        target.height = 0;
+        assert_eq!(
+            render_frame(&source(8, 8), &target, None).dimensions(),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn center_and_tile_keep_image_pixels() {
+        let img = source(20, 10);
+        let solid = LetterboxFill::Solid;
+        let center = render_frame(
+            &img,
+            &target(StyleMode::Center, solid, CropMode::Center),
+            None,
+        );
+        assert_eq!(center.get_pixel(0, 0), &Rgb([10, 20, 30]));
+        assert_eq!(center.get_pixel(22, 13), img.to_rgb8().get_pixel(0, 0));
+
+        let tile = render_frame(
+            &img,
+            &target(StyleMode::Tile, solid, CropMode::Center),
+            None,
+        );
+        assert_eq!(tile.get_pixel(43, 31), img.to_rgb8().get_pixel(3, 1));
+    }
+}
@@ -0,0 +1,201 @@
+//! Spanned wallpapers with a separate image on every display.
+//!
+//! Windows stretches a Span wallpaper over the bounding box of all monitors, so
+//! the canvas is that box and each image is cut to its display's rectangle in it.
+
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+use image::imageops;
+use image::{DynamicImage, Rgb, RgbImage};
+
+use super::crop::crop_to_fill;
+use super::layout::SlotOrientation;
+use super::render::filter_type;
+use super::{needs_rotation, open_image, rotate_focus, rotate_quarter, write_cache};
+use crate::focus;
+use crate::settings::{CropMode, ResampleFilter, RotateDirection, SpanDisplay};
+
+/// Largest canvas side accepted, to catch mistyped positions before allocating.
+const MAX_SPAN_SIDE: u32 = 32_768;
+
+/// Options for composing a spanned wallpaper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpanOptions {
+    /// Turn each image to its display's orientation in this direction.
+    pub auto_rotate: Option<RotateDirection>,
+    /// Part of each image kept when it is cut to its display.
+    pub crop_mode: CropMode,
+    /// Filter used to scale images to their displays.
+    pub filter: ResampleFilter,
+    /// Colour of desktop areas no display covers.
+    pub background: [u8; 3],
+}
+
+/// Bounding box `(x, y, width, height)` of all displays on the virtual desktop.
+pub fn span_bounds(displays: &[SpanDisplay]) -> Option<(i32, i32, u32, u32)> {
+    let left = displays.iter().map(|display| display.x).min()?;
+    let top = displays.iter().map(|display| display.y).min()?;
+    let (right, bottom) = displays
+        .iter()
+        .fold((left, top), |(right, bottom), display| {
+            let (width, height) = display.desktop_size();
+            (
+                right.max(display.x.saturating_add_unsigned(width)),
+                bottom.max(display.y.saturating_add_unsigned(height)),
+            )
+        });
+    Some((left, top, right.abs_diff(left), bottom.abs_diff(top)))
+}
+
+/// Compose one canvas covering every display, with `paths[i]` Fill-rendered to
+/// display `i`, and cache it.
+///
+/// Images repeat in order when there are fewer than displays. The canvas already
+/// matches the desktop, so it is saved without further rendering.
+pub fn span_images(
+    paths: &[PathBuf],
+    displays: &[SpanDisplay],
+    options: &SpanOptions,
+) -> Result<PathBuf> {
+    let canvas = span_canvas(paths, displays, options)?;
+    write_cache(&DynamicImage::ImageRgb8(canvas), None, None)
+}
+
+/// Canvas of [`span_images`] before it is cached.
+fn span_canvas(
+    paths: &[PathBuf],
+    displays: &[SpanDisplay],
+    options: &SpanOptions,
+) -> Result<RgbImage> {
+    if paths.is_empty() {
+        bail!("no images to span");
+    }
+    let Some((left, top, width, height)) = span_bounds(displays) else {
+        bail!("no displays to span");
+    };
+    if width == 0 || height == 0 || width > MAX_SPAN_SIDE || height > MAX_SPAN_SIDE {
+        bail!("display layout spans {width} x {height} pixels");
+    }
+
+    let focus_map = focus::shared();
+    let filter = filter_type(options.filter);
+    let mut canvas = RgbImage::from_pixel(width, height, Rgb(options.background));
+    for (display, path) in displays.iter().zip(paths.iter().cycle()) {
+        let (display_width, display_height) = display.desktop_size();
+        if display_width == 0 || display_height == 0 {
+            continue;
+        }
+        let mut img = open_image(path)?;
+        let mut focus = focus_map.point(path);
+        if let Some(direction) = options.auto_rotate {
+            let target = if display_width < display_height {
+                SlotOrientation::Portrait
+            } else {
+                SlotOrientation::Landscape
+            };
+            if needs_rotation(&img, target) {
+                img = rotate_quarter(&img, direction);
+                focus = focus.map(|point| rotate_focus(point, direction));
+            }
+        }
+        let frame = crop_to_fill(
+            &img.to_rgb8(),
+            display_width,
+            display_height,
+            options.crop_mode,
+            focus,
+            filter,
+        );
+        let x = display.x as i64 - left as i64;
+        let y = display.y as i64 - top as i64;
+        imageops::replace(&mut canvas, &frame, x, y);
+    }
+    Ok(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::DisplayRotation;
+    use crate::testing::TempDir;
+
+    const OPTIONS: SpanOptions = SpanOptions {
+        auto_rotate: None,
+        crop_mode: CropMode::Center,
+        filter: ResampleFilter::Triangle,
+        background: [0, 0, 0],
+    };
+
+    fn display(x: i32, y: i32, width: u32, height: u32) -> SpanDisplay {
+        SpanDisplay {
+            x,
+            y,
+            width,
+            height,
+            rotation: DisplayRotation::Landscape,
+        }
+    }
+
+    /// Save a solid `color` image and return its path.
+    fn solid(root: &TempDir, name: &str, color: [u8; 3]) -> PathBuf {
+        let path = root.path().join(name);
+        RgbImage::from_pixel(40, 30, Rgb(color))
+            .save(&path)
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn bounds_cover_displays_left_of_and_above_the_primary() {
+        let mut portrait = display(-1080, -420, 1920, 1080);
+        portrait.rotation = DisplayRotation::Portrait;
+        let displays = [display(0, 0, 1920, 1080), portrait];
+        assert_eq!(span_bounds(&displays), Some((-1080, -420, 3000, 1920)));
+        assert_eq!(
+            span_bounds(&[display(100, 50, 800, 600)]),
+            Some((100, 50, 800, 600))
+        );
+        assert_eq!(span_bounds(&[]), None);
+    }
+
+    #[test]
+    fn each_display_gets_its_image_in_its_rectangle() {
+        let root = TempDir::new("span");
+        let red = solid(&root, "red.png", [255, 0, 0]);
+        let blue = solid(&root, "blue.png", [0, 0, 255]);
+        // A secondary display to the left and higher than the primary.
+        let displays = [display(0, 0, 40, 30), display(-20, -10, 20, 20)];
+        let canvas = span_canvas(&[red, blue], &displays, &OPTIONS).unwrap();
+
+        assert_eq!(canvas.dimensions(), (60, 40));
+        assert_eq!(canvas.get_pixel(0, 0).0, [0, 0, 255]);
+        assert_eq!(canvas.get_pixel(19, 19).0, [0, 0, 255]);
+        assert_eq!(canvas.get_pixel(20, 10).0, [255, 0, 0]);
+        assert_eq!(canvas.get_pixel(59, 39).0, [255, 0, 0]);
+        // Below the secondary display nothing is covered.
+        assert_eq!(canvas.get_pixel(5, 35).0, OPTIONS.background);
+    }
+
+    #[test]
+    fn images_repeat_when_displays_outnumber_them() {
+        let root = TempDir::new("span");
+        let green = solid(&root, "green.png", [0, 255, 0]);
+        let displays = [display(0, 0, 10, 10), display(10, 0, 10, 10)];
+        let canvas = span_canvas(&[green], &displays, &OPTIONS).unwrap();
+        assert!(canvas.pixels().all(|pixel| pixel.0 == [0, 255, 0]));
+    }
+
+    #[test]
+    fn rejects_missing_or_oversized_layouts() {
+        let root = TempDir::new("span");
+        let red = solid(&root, "red.png", [255, 0, 0]);
+        assert!(span_canvas(std::slice::from_ref(&red), &[], &OPTIONS).is_err());
+        assert!(span_canvas(&[], &[display(0, 0, 10, 10)], &OPTIONS).is_err());
+        let far = [
+            display(0, 0, 10, 10),
+            display(MAX_SPAN_SIDE as i32, 0, 10, 10),
+        ];
+        assert!(span_canvas(&[red], &far, &OPTIONS).is_err());
+    }
+}
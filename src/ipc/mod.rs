@@ -0,0 +1,217 @@
+//! Local control channel so the command line can drive a running instance.
+//!
+//! The app listens on a loopback TCP port and writes the port and a random token to
+//! `ipc.json` in the config directory. A client sends one line, `<token> <command>`,
+//! and reads one reply line: `ok` or `error: <message>`.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::library;
+use crate::session::{self, PinRequest};
+
+/// File name used under the per-user config directory.
+const IPC_FILE: &str = "ipc.json";
+
+/// How long either side waits on a connection before giving up.
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest request line accepted from a client.
+const MAX_REQUEST_BYTES: u64 = 256;
+
+/// Actions other processes can ask the running app to perform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteCommand {
+    /// Advance to the next wallpaper.
+    Next,
+    /// Keep the current wallpaper for a while.
+    Pin(PinRequest),
+    /// Resume normal rotation.
+    Unpin,
+}
+
+impl RemoteCommand {
+    /// Parse the protocol form: `next`, `pin <duration>`, or `unpin`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut words = text.split_whitespace();
+        let command = match (words.next(), words.next()) {
+            (Some("next"), None) => RemoteCommand::Next,
+            (Some("pin"), Some(duration)) => RemoteCommand::Pin(PinRequest::parse(duration)?),
+            (Some("unpin"), None) => RemoteCommand::Unpin,
+            _ => bail!("unknown command `{}`", text.trim()),
+        };
+        if words.next().is_some() {
+            bail!("unexpected arguments in `{}`", text.trim());
+        }
+        Ok(command)
+    }
+
+    /// Protocol form accepted by [`RemoteCommand::parse`].
+    pub fn to_line(self) -> String {
+        match self {
+            RemoteCommand::Next => "next".to_string(),
+            RemoteCommand::Pin(request) => format!("pin {}", request.to_arg()),
+            RemoteCommand::Unpin => "unpin".to_string(),
+        }
+    }
+
+    /// Read a command from CLI arguments: `--next`, `--pin <duration>`, or `--unpin`.
+    pub fn from_args(args: &[String]) -> Result<Option<Self>> {
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--next" => return Ok(Some(RemoteCommand::Next)),
+                "--unpin" => return Ok(Some(RemoteCommand::Unpin)),
+                "--pin" => {
+                    let duration = iter
+                        .next()
+                        .ok_or_else(|| anyhow!("--pin needs a duration such as 1h or tomorrow"))?;
+                    return Ok(Some(RemoteCommand::Pin(PinRequest::parse(duration)?)));
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Why a command did not reach a running instance.
+#[derive(Debug)]
+pub enum SendError {
+    /// No instance is listening: it never started or has exited.
+    NotRunning,
+    /// The instance was reached but the exchange failed or it rejected the command.
+    Failed(anyhow::Error),
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::NotRunning => write!(f, "wallpaper manager is not running"),
+            SendError::Failed(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for SendError {}
+
+impl From<io::Error> for SendError {
+    fn from(err: io::Error) -> Self {
+        SendError::Failed(err.into())
+    }
+}
+
+/// Where a running instance listens.
+#[derive(Debug, Serialize, Deserialize)]
+struct Endpoint {
+    port: u16,
+    token: String,
+}
+
+/// Start listening for commands; `handler` runs on the listener thread.
+pub fn serve(handler: impl Fn(RemoteCommand) + Send + 'static) -> Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let endpoint = Endpoint {
+        port: listener.local_addr()?.port(),
+        token: format!("{:032x}", rand::random::<u128>()),
+    };
+    fs::write(ipc_path()?, serde_json::to_string_pretty(&endpoint)?)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A misbehaving client must not take the listener down.
+            let _ = handle_client(stream, &endpoint.token, &handler);
+        }
+    });
+    Ok(())
+}
+
+/// Run a CLI command against the running instance.
+///
+/// Pins still apply without a running instance: they are saved and honored when
+/// the slideshow next starts. Any other failure, including an error reply from
+/// the instance, is returned so the process exits non-zero.
+pub fn run_cli(command: RemoteCommand) -> Result<()> {
+    match (send(command), command) {
+        (Ok(()), _) => Ok(()),
+        (Err(SendError::NotRunning), RemoteCommand::Pin(request)) => {
+            session::set_pin(Some(request.resolve(library::unix_now())))
+        }
+        (Err(SendError::NotRunning), RemoteCommand::Unpin) => session::set_pin(None),
+        (Err(err), _) => Err(err.into()),
+    }
+}
+
+/// Send one command to the running instance and wait for its reply.
+pub fn send(command: RemoteCommand) -> Result<(), SendError> {
+    let path = ipc_path().map_err(SendError::Failed)?;
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Err(SendError::NotRunning),
+        Err(err) => return Err(err.into()),
+    };
+    let endpoint: Endpoint =
+        serde_json::from_str(&contents).map_err(|err| SendError::Failed(err.into()))?;
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, endpoint.port));
+    // The endpoint file outlives an instance that crashed; nothing listens then.
+    let mut stream = match TcpStream::connect_timeout(&addr, IO_TIMEOUT) {
+        Ok(stream) => stream,
+        Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => {
+            return Err(SendError::NotRunning);
+        }
+        Err(err) => return Err(err.into()),
+    };
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    writeln!(stream, "{} {}", endpoint.token, command.to_line())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        "" => Err(SendError::Failed(anyhow!(
+            "no reply from wallpaper manager"
+        ))),
+        other => Err(SendError::Failed(anyhow!(
+            "{}",
+            other.strip_prefix("error: ").unwrap_or(other)
+        ))),
+    }
+}
+
+/// Read one request, run it, and write the reply.
+fn handle_client(stream: TcpStream, token: &str, handler: &impl Fn(RemoteCommand)) -> Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST_BYTES)).read_line(&mut line)?;
+
+    let reply = match line.trim().split_once(' ') {
+        Some((given, request)) if given == token => match RemoteCommand::parse(request) {
+            Ok(command) => {
+                handler(command);
+                "ok".to_string()
+            }
+            Err(err) => format!("error: {err}"),
+        },
+        _ => "error: invalid token".to_string(),
+    };
+    writeln!(&stream, "{reply}")?;
+    Ok(())
+}
+
+/// Build the endpoint file path and ensure the directory exists.
+fn ipc_path() -> Result<PathBuf> {
+    let proj_dirs = ProjectDirs::from("dev", "wallpaper_manager", "wallpaper_manager")
+        .ok_or_else(|| anyhow!("cannot determine config directory"))?;
+    let config_dir = proj_dirs.config_dir();
+    fs::create_dir_all(config_dir)?;
+    Ok(config_dir.join(IPC_FILE))
+}
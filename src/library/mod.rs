@@ -0,0 +1,176 @@
+//! Per-image ratings and play statistics persisted beside the settings file.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+/// File name used under the per-user config directory.
+const LIBRARY_FILE: &str = "library.json";
+
+/// Highest rating a user can assign.
+pub const MAX_RATING: u8 = 5;
+
+/// Seconds in one day, used by the day-based repeat window.
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Library handle shared between the UI thread and the slideshow worker.
+pub type SharedLibrary = Arc<Mutex<ImageLibrary>>;
+
+/// How long a shown image stays excluded from random selection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RepeatWindow {
+    /// Exclude images shown within this many days (0 disables).
+    pub days: u32,
+    /// Exclude images shown within this many wallpaper changes (0 disables).
+    pub changes: u32,
+}
+
+/// Stored statistics for a single image.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ImageRecord {
+    /// User rating in range [0, 5]; 0 means unrated.
+    pub rating: u8,
+    /// Number of times the image has been applied as wallpaper.
+    pub show_count: u32,
+    /// Unix time (seconds) of the most recent showing.
+    pub last_shown: Option<u64>,
+    /// Library change sequence number of the most recent showing.
+    pub last_change: Option<u64>,
+}
+
+/// All image records keyed by absolute path.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ImageLibrary {
+    /// Records for images that have been rated or shown at least once.
+    pub images: HashMap<String, ImageRecord>,
+    /// Total wallpaper changes recorded; a stitched wallpaper counts once.
+    pub change_count: u64,
+}
+
+impl ImageLibrary {
+    /// Look up the record for an image, if one exists.
+    pub fn record(&self, path: &Path) -> Option<&ImageRecord> {
+        self.images.get(&library_key(path))
+    }
+
+    /// Return the user rating for an image (0 when unrated).
+    pub fn rating(&self, path: &Path) -> u8 {
+        self.record(path).map_or(0, |record| record.rating)
+    }
+
+    /// Store a user rating, clamped to [0, 5].
+    pub fn set_rating(&mut self, path: &Path, rating: u8) {
+        self.images.entry(library_key(path)).or_default().rating = rating.min(MAX_RATING);
+    }
+
+    /// Record one wallpaper change made of the given images.
+    pub fn record_change(&mut self, paths: &[PathBuf], now: u64) {
+        self.change_count += 1;
+        for path in paths {
+            let record = self.images.entry(library_key(path)).or_default();
+            record.show_count = record.show_count.saturating_add(1);
+            record.last_shown = Some(now);
+            record.last_change = Some(self.change_count);
+        }
+    }
+
+    /// Return true when the image was shown inside the repeat window.
+    pub fn is_recent(&self, path: &Path, window: RepeatWindow, now: u64) -> bool {
+        let Some(record) = self.record(path) else {
+            return false;
+        };
+        let within_changes = window.changes > 0
+            && record
+                .last_change
+                .is_some_and(|change| self.change_count - change < window.changes as u64);
+        let within_days = window.days > 0
+            && record
+                .last_shown
+                .is_some_and(|shown| now.saturating_sub(shown) < window.days as u64 * SECS_PER_DAY);
+        within_changes || within_days
+    }
+
+    /// Change sequence number of the last showing, 0 when never shown.
+    pub fn last_change(&self, path: &Path) -> u64 {
+        self.record(path)
+            .and_then(|record| record.last_change)
+            .unwrap_or(0)
+    }
+
+    /// Relative weight for weighted random selection.
+    ///
+    /// Each rating step adds one unit, and the result is divided by the square root
+    /// of the show count so often-seen images fade without disappearing.
+    pub fn selection_weight(&self, path: &Path) -> f64 {
+        let (rating, shown) = self
+            .record(path)
+            .map_or((0, 0), |record| (record.rating, record.show_count));
+        (1.0 + rating as f64) / (1.0 + shown as f64).sqrt()
+    }
+}
+
+/// Lock a shared library, recovering the data if another thread panicked.
+pub fn lock(library: &SharedLibrary) -> MutexGuard<'_, ImageLibrary> {
+    library
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Record a wallpaper change at the current time and persist the result.
+pub fn record_change(library: &SharedLibrary, paths: &[PathBuf]) -> Result<()> {
+    let mut library = lock(library);
+    library.record_change(paths, unix_now());
+    save(&library)
+}
+
+/// Current wall-clock time as Unix seconds.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Normalize a path into the string used as the library key.
+fn library_key(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// Build the library path and ensure the directory exists.
+fn library_path() -> Result<PathBuf> {
+    let proj_dirs = ProjectDirs::from("dev", "wallpaper_manager", "wallpaper_manager")
+        .ok_or_else(|| anyhow!("cannot determine config directory"))?;
+    let config_dir = proj_dirs.config_dir();
+    fs::create_dir_all(config_dir)?;
+    Ok(config_dir.join(LIBRARY_FILE))
+}
+
+/// Load the library from disk, returning an empty library when missing.
+pub fn load() -> ImageLibrary {
+    let path = match library_path() {
+        Ok(path) => path,
+        Err(_) => return ImageLibrary::default(),
+    };
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return ImageLibrary::default(),
+    };
+    serde_json::from_str(&contents).unwrap_or_default()
+}
+
+/// Persist the library to disk as compact JSON.
+pub fn save(library: &ImageLibrary) -> Result<()> {
+    let path = library_path()?;
+    // Compact output keeps large libraries cheap to rewrite after every change.
+    let contents = serde_json::to_string(library)?;
+    fs::write(path, contents)?;
+    Ok(())
+}
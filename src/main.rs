@@ -2,21 +2,44 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod app;
+mod focus;
 mod i18n;
 mod image_ops;
+mod ipc;
+mod library;
+mod monitor;
+mod schedule;
+mod session;
 mod settings;
 mod slideshow;
 mod startup;
 mod state;
+#[cfg(test)]
+mod testing;
 mod theme;
 mod wallpaper;
 
+/// Arguments that run a command-line action instead of opening a window.
+const CLI_FLAGS: [&str; 4] = ["--next", "--pin", "--unpin", "--preview"];
+
 /// Configure the native window and start the egui runtime.
 fn main() -> anyhow::Result<()> {
+    // Control commands are forwarded to the running instance instead of opening a window.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| CLI_FLAGS.contains(&arg.as_str())) {
+        attach_parent_console();
+    }
+    if let Some(command) = ipc::RemoteCommand::from_args(&args)? {
+        return ipc::run_cli(command);
+    }
+    if let Some(count) = slideshow::preview::count_from_args(&args)? {
+        return slideshow::preview::run_cli(count);
+    }
+
     // Clean up temp files from previous runs
     image_ops::cleanup_temp_files();
 
-    let started_from_startup = std::env::args().any(|arg| arg == "--startup");
+    let started_from_startup = args.iter().any(|arg| arg == "--startup");
     let native_options = eframe::NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default()
             .with_inner_size([920.0, 620.0])
@@ -30,3 +53,17 @@ fn main() -> anyhow::Result<()> {
     )?;
     Ok(())
 }
+
+/// Print through the console the app was started from, so CLI output and errors
+/// are seen; release builds use the Windows subsystem and start without one.
+#[cfg(windows)]
+fn attach_parent_console() {
+    use windows::Win32::System::Console::{ATTACH_PARENT_PROCESS, AttachConsole};
+
+    // Fails harmlessly when there is no parent console, e.g. launched from Explorer.
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+/// Other platforms always print to the terminal they were started from.
+#[cfg(not(windows))]
+fn attach_parent_console() {}
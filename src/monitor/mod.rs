@@ -0,0 +1,36 @@
+//! Environment probes (power source, running apps) polled off the worker thread.
+
+pub mod power;
+pub mod process;
+
+use std::sync::Weak;
+use std::thread;
+use std::time::Duration;
+
+/// Poll `probe` every `period` and pass each reading that differs from the last
+/// one (starting from `initial`) to `notify`.
+///
+/// Polling stops once `owner` is dropped or `notify` returns false.
+pub fn watch<T, P, N>(owner: Weak<()>, period: Duration, initial: T, mut probe: P, mut notify: N)
+where
+    T: PartialEq + Send + 'static,
+    P: FnMut() -> T + Send + 'static,
+    N: FnMut(&T) -> bool + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut last = initial;
+        loop {
+            thread::sleep(period);
+            if owner.strong_count() == 0 {
+                break;
+            }
+            let reading = probe();
+            if reading != last {
+                if !notify(&reading) {
+                    break;
+                }
+                last = reading;
+            }
+        }
+    });
+}
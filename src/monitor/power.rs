@@ -0,0 +1,162 @@
+//! AC/battery detection.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the machine currently draws power from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerSource {
+    /// Mains power, or a machine without a battery.
+    Ac,
+    /// Running on battery.
+    Battery,
+}
+
+/// Default sysfs directory listing power supplies on Linux.
+pub const SYSFS_POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+/// Reads the power source from the operating system.
+#[derive(Debug, Clone)]
+pub struct PowerMonitor {
+    /// Directory laid out like `/sys/class/power_supply`; ignored on Windows.
+    #[cfg_attr(windows, allow(dead_code))]
+    root: PathBuf,
+}
+
+impl PowerMonitor {
+    /// Read power supplies from `root`, normally [`SYSFS_POWER_SUPPLY`]; tests can
+    /// point it at a fixture directory.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Current power source; machines that report nothing count as AC.
+    pub fn read(&self) -> PowerSource {
+        #[cfg(windows)]
+        let source = read_windows();
+        #[cfg(not(windows))]
+        let source = read_sysfs(&self.root);
+        source.unwrap_or(PowerSource::Ac)
+    }
+}
+
+/// Read the power source from a sysfs-style `power_supply` directory.
+///
+/// Any online mains adapter means AC. Otherwise a discharging system battery, or a
+/// system battery next to offline adapters, means battery. Device batteries (mice,
+/// headsets) are ignored.
+pub fn read_sysfs(root: &Path) -> Option<PowerSource> {
+    let mut mains_seen = false;
+    let mut battery_seen = false;
+    let mut discharging = false;
+
+    for entry in fs::read_dir(root).ok()?.flatten() {
+        let dir = entry.path();
+        match read_attr(&dir, "type").as_deref() {
+            Some("Mains") | Some("USB") => {
+                mains_seen = true;
+                if read_attr(&dir, "online").as_deref() == Some("1") {
+                    return Some(PowerSource::Ac);
+                }
+            }
+            Some("Battery") => {
+                if read_attr(&dir, "scope").as_deref() == Some("Device") {
+                    continue;
+                }
+                battery_seen = true;
+                discharging |= read_attr(&dir, "status").as_deref() == Some("Discharging");
+            }
+            _ => {}
+        }
+    }
+
+    match (battery_seen, discharging || mains_seen) {
+        (true, true) => Some(PowerSource::Battery),
+        (true, false) => Some(PowerSource::Ac),
+        (false, _) => None,
+    }
+}
+
+/// Read one trimmed sysfs attribute.
+fn read_attr(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+/// Read the AC line status reported by Windows.
+#[cfg(windows)]
+fn read_windows() -> Option<PowerSource> {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    unsafe { GetSystemPowerStatus(&mut status) }.ok()?;
+    match status.ACLineStatus {
+        0 => Some(PowerSource::Battery),
+        1 => Some(PowerSource::Ac),
+        // 255 means unknown.
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    /// Directory laid out like `/sys/class/power_supply`.
+    fn fixture(supplies: &[(&str, &[(&str, &str)])]) -> TempDir {
+        let root = TempDir::new("power");
+        for (supply, attrs) in supplies {
+            for (attr, value) in *attrs {
+                root.write(Path::new(supply).join(attr), format!("{value}\n"));
+            }
+        }
+        root
+    }
+
+    #[test]
+    fn online_adapter_means_ac() {
+        let root = fixture(&[
+            ("AC", &[("type", "Mains"), ("online", "1")]),
+            ("BAT0", &[("type", "Battery"), ("status", "Discharging")]),
+        ]);
+        assert_eq!(read_sysfs(root.path()), Some(PowerSource::Ac));
+    }
+
+    #[test]
+    fn offline_adapter_with_battery_means_battery() {
+        let root = fixture(&[
+            ("ADP1", &[("type", "Mains"), ("online", "0")]),
+            ("BAT0", &[("type", "Battery"), ("status", "Full")]),
+        ]);
+        assert_eq!(read_sysfs(root.path()), Some(PowerSource::Battery));
+    }
+
+    #[test]
+    fn lone_battery_follows_its_status() {
+        let discharging = fixture(&[("BAT0", &[("type", "Battery"), ("status", "Discharging")])]);
+        assert_eq!(read_sysfs(discharging.path()), Some(PowerSource::Battery));
+        let charging = fixture(&[("BAT0", &[("type", "Battery"), ("status", "Charging")])]);
+        assert_eq!(read_sysfs(charging.path()), Some(PowerSource::Ac));
+    }
+
+    #[test]
+    fn device_batteries_are_ignored() {
+        let root = fixture(&[(
+            "hid-mouse-battery",
+            &[
+                ("type", "Battery"),
+                ("scope", "Device"),
+                ("status", "Discharging"),
+            ],
+        )]);
+        assert_eq!(read_sysfs(root.path()), None);
+    }
+
+    #[test]
+    fn missing_root_reports_nothing() {
+        let root = fixture(&[]);
+        assert_eq!(read_sysfs(&root.path().join("absent")), None);
+    }
+}
@@ -0,0 +1,172 @@
+//! Detection of running applications that should hold the wallpaper.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default procfs mount on Linux.
+pub const PROCFS_ROOT: &str = "/proc";
+
+/// Reports whether any watched application is running.
+#[derive(Debug, Clone)]
+pub struct ProcessMonitor {
+    /// Directory laid out like `/proc`; ignored on Windows.
+    #[cfg_attr(windows, allow(dead_code))]
+    root: PathBuf,
+    /// Normalized names to look for.
+    names: Vec<String>,
+}
+
+impl ProcessMonitor {
+    /// Watch for `names` (case-insensitive, `.exe` optional), reading processes from
+    /// `root`, normally [`PROCFS_ROOT`]; tests can point it at a fixture directory.
+    pub fn new(root: impl Into<PathBuf>, names: &[String]) -> Self {
+        Self {
+            root: root.into(),
+            names: names
+                .iter()
+                .map(|name| normalize(name))
+                .filter(|name| !name.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Name of the first watched application found running, if any.
+    pub fn read(&self) -> Option<String> {
+        if self.names.is_empty() {
+            return None;
+        }
+        #[cfg(windows)]
+        let running = windows_process_names();
+        #[cfg(not(windows))]
+        let running = procfs_process_names(&self.root);
+        running
+            .into_iter()
+            .map(|name| normalize(&name))
+            .find(|name| self.names.contains(name))
+    }
+}
+
+/// Lowercase a process name and drop any directory and `.exe` suffix.
+fn normalize(name: &str) -> String {
+    let name = name.trim();
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let lower = base.to_lowercase();
+    lower
+        .strip_suffix(".exe")
+        .map(str::to_string)
+        .unwrap_or(lower)
+}
+
+/// Names of processes listed under a procfs-style directory.
+///
+/// `comm` is truncated to 15 bytes by the kernel, so the executable named in
+/// `cmdline` is reported as well.
+pub fn procfs_process_names(root: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut names = Vec::new();
+    for entry in entries.flatten() {
+        let is_pid = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()));
+        if !is_pid {
+            continue;
+        }
+        let dir = entry.path();
+        if let Ok(comm) = fs::read_to_string(dir.join("comm")) {
+            names.push(comm.trim().to_string());
+        }
+        if let Ok(cmdline) = fs::read(dir.join("cmdline")) {
+            let exe = cmdline.split(|&b| b == 0).next().unwrap_or_default();
+            if !exe.is_empty() {
+                names.push(String::from_utf8_lossy(exe).to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Executable names of all processes, from a Toolhelp snapshot.
+#[cfg(windows)]
+fn windows_process_names() -> Vec<String> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW,
+        TH32CS_SNAPPROCESS,
+    };
+
+    let mut names = Vec::new();
+    unsafe {
+        let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) else {
+            return names;
+        };
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut next = Process32FirstW(snapshot, &mut entry);
+        while next.is_ok() {
+            let len = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            names.push(String::from_utf16_lossy(&entry.szExeFile[..len]));
+            next = Process32NextW(snapshot, &mut entry);
+        }
+        let _ = CloseHandle(snapshot);
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    /// Directory laid out like `/proc`, with `(entry, comm, cmdline)` per process.
+    fn fixture(processes: &[(&str, &str, &[u8])]) -> TempDir {
+        let root = TempDir::new("proc");
+        for (entry, comm, cmdline) in processes {
+            root.write(Path::new(entry).join("comm"), format!("{comm}\n"));
+            root.write(Path::new(entry).join("cmdline"), cmdline);
+        }
+        root
+    }
+
+    #[test]
+    fn reads_comm_and_executable_of_pid_entries() {
+        let root = fixture(&[
+            ("1", "systemd", b"/sbin/init\0splash\0"),
+            ("42", "vlc", b""),
+            ("self", "ignored", b"ignored\0"),
+            ("sys", "ignored", b""),
+        ]);
+        let mut names = procfs_process_names(root.path());
+        names.sort();
+        assert_eq!(names, ["/sbin/init", "systemd", "vlc"]);
+    }
+
+    #[test]
+    fn missing_root_lists_nothing() {
+        let root = fixture(&[]);
+        assert!(procfs_process_names(&root.path().join("absent")).is_empty());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn monitor_matches_truncated_comm_through_cmdline() {
+        let root = fixture(&[(
+            "7",
+            "longapplicatio",
+            b"/opt/app/LongApplicationName\0--flag\0",
+        )]);
+        let names = ["longapplicationname.exe".to_string()];
+        let monitor = ProcessMonitor::new(root.path(), &names);
+        assert_eq!(monitor.read().as_deref(), Some("longapplicationname"));
+        let other = ProcessMonitor::new(root.path(), &["vlc".to_string()]);
+        assert_eq!(other.read(), None);
+    }
+}
@@ -0,0 +1,241 @@
+//! Five-field cron expressions evaluated against the local wall clock.
+//!
+//! Fields are `minute hour day-of-month month day-of-week`. Each field accepts `*`,
+//! numbers, ranges (`1-5`), steps (`*/15`, `8-18/2`), and comma lists. Months and
+//! weekdays also accept three-letter English names, and Sunday is 0 or 7. As in
+//! classic cron, a job runs when either day field matches if both are restricted;
+//! a day field starting with `*` (including `*/2`) counts as unrestricted, and
+//! then both fields must match.
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{Datelike, Duration, Local, NaiveDateTime, TimeZone, Timelike};
+
+/// Days searched for the next match; covers leap days and rare weekday/date combos.
+const SEARCH_DAYS: i64 = 4 * 366 + 7;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Parsed cron expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day-of-month field started with `*`.
+    any_day: bool,
+    /// Whether the day-of-week field started with `*`.
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// Parse a five-field cron expression.
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            bail!("cron expression needs 5 fields, found {}", fields.len());
+        };
+        let mut weekdays = parse_field(weekday, 0, 7, &WEEKDAY_NAMES)?;
+        // Both 0 and 7 mean Sunday.
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[])?,
+            hours: parse_field(hour, 0, 23, &[])?,
+            days: parse_field(day, 1, 31, &[])?,
+            months: parse_field(month, 1, 12, &MONTH_NAMES)?,
+            weekdays,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        })
+    }
+
+    /// First matching minute strictly after `now` (Unix seconds), as Unix seconds.
+    pub fn next_after(&self, now: u64) -> Option<u64> {
+        let local = Local.timestamp_opt(now as i64, 0).single()?.naive_local();
+        let mut at = local.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = local + Duration::days(SEARCH_DAYS);
+
+        while at < limit {
+            if !self.matches_date(&at) {
+                at = (at.date() + Duration::days(1)).and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !bit(self.hours, at.hour()) {
+                at = at.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if !bit(self.minutes, at.minute()) {
+                at += Duration::minutes(1);
+                continue;
+            }
+            // Local times skipped by a DST jump never happen; keep searching.
+            match Local.from_local_datetime(&at).earliest() {
+                Some(resolved) if resolved.timestamp() > now as i64 => {
+                    return Some(resolved.timestamp() as u64);
+                }
+                _ => at += Duration::minutes(1),
+            }
+        }
+        None
+    }
+
+    /// Return true when the month and day fields accept the date.
+    fn matches_date(&self, at: &NaiveDateTime) -> bool {
+        if !bit(self.months, at.month()) {
+            return false;
+        }
+        let day = bit(self.days, at.day());
+        let weekday = bit(self.weekdays, at.weekday().num_days_from_sunday());
+        if self.any_day || self.any_weekday {
+            day && weekday
+        } else {
+            day || weekday
+        }
+    }
+}
+
+/// Parse one field into a bit set of allowed values.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let mut set = 0_u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| anyhow!("invalid step `{step}` in `{field}`"))?;
+                if step == 0 {
+                    bail!("step cannot be 0 in `{field}`");
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_value(start, min, max, names)?,
+                parse_value(end, min, max, names)?,
+            )
+        } else {
+            let value = parse_value(range, min, max, names)?;
+            // `5/10` means "from 5 to the end, every 10".
+            (value, if step > 1 { max } else { value })
+        };
+        if start > end {
+            bail!("range `{range}` runs backwards");
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+/// Parse a number or name and check it is inside the field range.
+fn parse_value(text: &str, min: u32, max: u32, names: &[&str]) -> Result<u32> {
+    let lower = text.to_ascii_lowercase();
+    let value = match names.iter().position(|name| *name == lower) {
+        // Month names start at 1, weekday names at 0 (Sunday).
+        Some(idx) => idx as u32 + min,
+        None => text
+            .parse()
+            .map_err(|_| anyhow!("invalid value `{text}`"))?,
+    };
+    if !(min..=max).contains(&value) {
+        bail!("`{text}` is outside {min}-{max}");
+    }
+    Ok(value)
+}
+
+/// Return true when `value` is in the bit set.
+fn bit(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Unix time of a local wall-clock time.
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> u64 {
+        let at = NaiveDate::from_ymd_opt(year, month, day)
+            .and_then(|date| date.and_hms_opt(hour, minute, 0))
+            .unwrap();
+        Local
+            .from_local_datetime(&at)
+            .earliest()
+            .unwrap()
+            .timestamp() as u64
+    }
+
+    fn next(expression: &str, now: u64) -> u64 {
+        CronSchedule::parse(expression)
+            .unwrap()
+            .next_after(now)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_rejects_malformed_expressions() {
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 * foo *").is_err());
+    }
+
+    #[test]
+    fn parse_accepts_names_and_both_sundays() {
+        let names = CronSchedule::parse("0 9 * JAN,jul sun").unwrap();
+        let numbers = CronSchedule::parse("0 9 * 1,7 7").unwrap();
+        assert_eq!(names, numbers);
+        assert_eq!(numbers, CronSchedule::parse("0 9 * 1,7 0").unwrap());
+    }
+
+    #[test]
+    fn next_after_steps_and_ranges() {
+        let now = local(2025, 1, 15, 10, 7);
+        assert_eq!(next("*/15 * * * *", now), local(2025, 1, 15, 10, 15));
+        assert_eq!(next("0 8-18/2 * * *", now), local(2025, 1, 15, 12, 0));
+        assert_eq!(next("30 9 * * *", now), local(2025, 1, 16, 9, 30));
+    }
+
+    #[test]
+    fn next_after_is_strictly_later() {
+        let now = local(2025, 1, 15, 10, 15);
+        assert_eq!(next("15 10 * * *", now), local(2025, 1, 16, 10, 15));
+        assert_eq!(next("* * * * *", now + 30), local(2025, 1, 15, 10, 16));
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // Wednesday 2025-01-15; the 20th is a Monday, Friday is the 17th.
+        let now = local(2025, 1, 15, 12, 0);
+        assert_eq!(next("0 0 20 * fri", now), local(2025, 1, 17, 0, 0));
+        assert_eq!(next("0 0 16 * mon", now), local(2025, 1, 16, 0, 0));
+    }
+
+    #[test]
+    fn starred_day_field_requires_both() {
+        let now = local(2025, 1, 15, 12, 0);
+        // Only the weekday restricts the date when the day of month is `*`.
+        assert_eq!(next("0 0 * * fri", now), local(2025, 1, 17, 0, 0));
+        // `*/2` also counts as unrestricted: an odd day that is a Monday.
+        assert_eq!(next("0 0 */2 * mon", now), local(2025, 1, 27, 0, 0));
+        assert_eq!(next("0 0 1 * */3", now), local(2025, 2, 1, 0, 0));
+    }
+
+    #[test]
+    fn next_after_finds_leap_days() {
+        let now = local(2025, 3, 1, 0, 0);
+        assert_eq!(next("0 12 29 feb *", now), local(2028, 2, 29, 12, 0));
+    }
+}
@@ -0,0 +1,400 @@
+//! Time-of-day evaluation of source schedule rules.
+
+pub mod cron;
+pub mod solar;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike};
+
+use crate::image_ops::FolderSource;
+use crate::settings::{GeoLocation, ScheduleRule, TimeAnchor};
+
+/// Minutes in one day; rule minutes are clamped to `0..=MINUTES_PER_DAY`.
+const MINUTES_PER_DAY: i32 = 24 * 60;
+/// Largest offset, in minutes, allowed from a sun event.
+pub const MAX_ANCHOR_OFFSET: i32 = 12 * 60;
+/// Days searched ahead for the next boundary, enough to cover a full week.
+const BOUNDARY_SEARCH_DAYS: u64 = 8;
+
+/// Indices of the rules active at `now` (Unix seconds), in priority order.
+pub fn active_rules(rules: &[ScheduleRule], location: GeoLocation, now: u64) -> Vec<usize> {
+    let Some(local) = local_time(now) else {
+        return Vec::new();
+    };
+    (0..rules.len())
+        .filter(|&idx| is_active(&rules[idx], location, &local))
+        .collect()
+}
+
+/// Time until the next rule window starts or ends, if any rule exists.
+///
+/// Boundaries are reported even when they do not change the active rule; callers
+/// re-evaluate with [`active_rules`] when the returned wait elapses.
+pub fn until_next_boundary(
+    rules: &[ScheduleRule],
+    location: GeoLocation,
+    now: u64,
+) -> Option<Duration> {
+    let today = local_time(now)?.date_naive();
+    let now = now as i64;
+    let mut next: Option<i64> = None;
+    for offset in 0..BOUNDARY_SEARCH_DAYS {
+        let Some(date) = today.checked_add_days(chrono::Days::new(offset)) else {
+            continue;
+        };
+        for rule in rules {
+            let Some((start, end)) = window(rule, location, date) else {
+                continue;
+            };
+            for minute in [start, end] {
+                let Some(at) = local_timestamp(date, minute) else {
+                    continue;
+                };
+                if at > now && next.is_none_or(|best| at < best) {
+                    next = Some(at);
+                }
+            }
+        }
+    }
+    next.map(|at| Duration::from_secs((at - now) as u64))
+}
+
+/// Images that belong to the source folders listed by a rule.
+pub fn rule_images(
+    rule: &ScheduleRule,
+    images: &[PathBuf],
+    folders: &[FolderSource],
+) -> Vec<PathBuf> {
+    let sources: Vec<&FolderSource> = folders
+        .iter()
+        .filter(|folder| {
+            rule.folders
+                .iter()
+                .any(|path| Path::new(path) == folder.path.as_path())
+        })
+        .collect();
+    images
+        .iter()
+        .filter(|image| sources.iter().any(|source| source.contains(image)))
+        .cloned()
+        .collect()
+}
+
+/// Format minutes after midnight as `HH:MM`.
+pub fn format_minute(minute: i32) -> String {
+    let minute = minute.clamp(0, MINUTES_PER_DAY);
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+/// Parse `HH:MM` into minutes after midnight.
+pub fn parse_minute(text: &str) -> Option<i32> {
+    let (hours, minutes) = text.trim().split_once(':')?;
+    let hours: i32 = hours.trim().parse().ok()?;
+    let minutes: i32 = minutes.trim().parse().ok()?;
+    if !(0..=24).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+    Some((hours * 60 + minutes).min(MINUTES_PER_DAY))
+}
+
+/// Minutes after local midnight of `date` at which a sun event occurs.
+///
+/// Returns `None` for [`TimeAnchor::Clock`] and on days the event does not happen.
+pub fn anchor_minute(anchor: TimeAnchor, location: GeoLocation, date: NaiveDate) -> Option<i32> {
+    let (altitude, rising) = match anchor {
+        TimeAnchor::Clock => return None,
+        TimeAnchor::CivilDawn => (solar::CIVIL_ALTITUDE, true),
+        TimeAnchor::Sunrise => (solar::HORIZON_ALTITUDE, true),
+        TimeAnchor::Sunset => (solar::HORIZON_ALTITUDE, false),
+        TimeAnchor::CivilDusk => (solar::CIVIL_ALTITUDE, false),
+    };
+    let (rise, set) = solar::crossings(date, location, altitude)?;
+    let at = Local
+        .timestamp_opt(if rising { rise } else { set }, 0)
+        .single()?;
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    Some(
+        at.naive_local()
+            .signed_duration_since(midnight)
+            .num_minutes() as i32,
+    )
+}
+
+/// Return true when the rule window covers the given local time.
+fn is_active(rule: &ScheduleRule, location: GeoLocation, local: &DateTime<Local>) -> bool {
+    let date = local.date_naive();
+    let minute = (local.hour() * 60 + local.minute()) as i32;
+    let today = window(rule, location, date).filter(|_| runs_on(rule, date));
+
+    if let Some((start, end)) = today {
+        if start == end {
+            return true;
+        }
+        if start < end {
+            return (start..end).contains(&minute);
+        }
+        if minute >= start {
+            return true;
+        }
+    }
+    // Overnight window: the tail belongs to the day the window started.
+    date.pred_opt()
+        .filter(|&prev| runs_on(rule, prev))
+        .and_then(|prev| window(rule, location, prev))
+        .is_some_and(|(start, end)| start > end && minute < end)
+}
+
+/// Rule window on `date` as clamped minutes after midnight.
+///
+/// Returns `None` when a sun anchor has no event that day (polar day or night).
+fn window(rule: &ScheduleRule, location: GeoLocation, date: NaiveDate) -> Option<(i32, i32)> {
+    Some((
+        boundary_minute(rule.start_anchor, rule.start_minute, location, date)?,
+        boundary_minute(rule.end_anchor, rule.end_minute, location, date)?,
+    ))
+}
+
+/// Resolve one rule boundary to minutes after midnight of `date`.
+fn boundary_minute(
+    anchor: TimeAnchor,
+    minute: i32,
+    location: GeoLocation,
+    date: NaiveDate,
+) -> Option<i32> {
+    let minute = match anchor {
+        TimeAnchor::Clock => minute,
+        _ => {
+            anchor_minute(anchor, location, date)?
+                + minute.clamp(-MAX_ANCHOR_OFFSET, MAX_ANCHOR_OFFSET)
+        }
+    };
+    Some(minute.clamp(0, MINUTES_PER_DAY))
+}
+
+/// Return true when the rule is enabled on the date's weekday.
+fn runs_on(rule: &ScheduleRule, date: NaiveDate) -> bool {
+    rule.weekdays[date.weekday().num_days_from_monday() as usize]
+}
+
+/// Convert a Unix timestamp into local time.
+fn local_time(now: u64) -> Option<DateTime<Local>> {
+    Local.timestamp_opt(now as i64, 0).single()
+}
+
+/// Unix timestamp of a local date plus minutes; `None` inside a DST gap.
+fn local_timestamp(date: NaiveDate, minute: i32) -> Option<i64> {
+    let naive = date.and_hms_opt(0, 0, 0)? + chrono::Duration::minutes(minute as i64);
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|local| local.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONDON: GeoLocation = GeoLocation {
+        latitude: 51.5,
+        longitude: -0.13,
+    };
+    /// Longyearbyen, in polar night around the December solstice.
+    const SVALBARD: GeoLocation = GeoLocation {
+        latitude: 78.2,
+        longitude: 15.6,
+    };
+
+    /// Unix time of a local wall-clock time.
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> u64 {
+        let at = NaiveDate::from_ymd_opt(year, month, day)
+            .and_then(|date| date.and_hms_opt(hour, minute, 0))
+            .unwrap();
+        Local
+            .from_local_datetime(&at)
+            .earliest()
+            .unwrap()
+            .timestamp() as u64
+    }
+
+    fn clock_rule(start_minute: i32, end_minute: i32) -> ScheduleRule {
+        ScheduleRule {
+            start_minute,
+            end_minute,
+            ..ScheduleRule::default()
+        }
+    }
+
+    fn only_on(mut rule: ScheduleRule, weekday: usize) -> ScheduleRule {
+        rule.weekdays = [false; 7];
+        rule.weekdays[weekday] = true;
+        rule
+    }
+
+    fn active(rules: &[ScheduleRule], now: u64) -> Vec<usize> {
+        active_rules(rules, LONDON, now)
+    }
+
+    #[test]
+    fn clock_window_includes_start_and_excludes_end() {
+        let rules = [clock_rule(7 * 60, 18 * 60)];
+        assert!(active(&rules, local(2025, 1, 15, 6, 59)).is_empty());
+        assert_eq!(active(&rules, local(2025, 1, 15, 7, 0)), [0]);
+        assert_eq!(active(&rules, local(2025, 1, 15, 17, 59)), [0]);
+        assert!(active(&rules, local(2025, 1, 15, 18, 0)).is_empty());
+    }
+
+    #[test]
+    fn equal_start_and_end_cover_the_whole_day() {
+        let rules = [clock_rule(9 * 60, 9 * 60)];
+        assert_eq!(active(&rules, local(2025, 1, 15, 0, 0)), [0]);
+        assert_eq!(active(&rules, local(2025, 1, 15, 23, 59)), [0]);
+    }
+
+    #[test]
+    fn rules_only_run_on_their_weekdays() {
+        // 2025-01-13 is a Monday.
+        let rules = [only_on(clock_rule(7 * 60, 18 * 60), 0)];
+        assert_eq!(active(&rules, local(2025, 1, 13, 12, 0)), [0]);
+        assert!(active(&rules, local(2025, 1, 14, 12, 0)).is_empty());
+    }
+
+    #[test]
+    fn overnight_window_wraps_past_midnight_into_the_next_day() {
+        let rules = [only_on(clock_rule(22 * 60, 6 * 60), 0)];
+        assert_eq!(active(&rules, local(2025, 1, 13, 23, 0)), [0]);
+        assert_eq!(active(&rules, local(2025, 1, 14, 0, 0)), [0]);
+        assert_eq!(active(&rules, local(2025, 1, 14, 5, 59)), [0]);
+        assert!(active(&rules, local(2025, 1, 14, 6, 0)).is_empty());
+        // The tail after Sunday midnight belongs to a window that never started.
+        assert!(active(&rules, local(2025, 1, 13, 5, 0)).is_empty());
+        assert!(active(&rules, local(2025, 1, 14, 23, 0)).is_empty());
+    }
+
+    #[test]
+    fn overlapping_rules_are_listed_in_order() {
+        let rules = [
+            clock_rule(12 * 60, 13 * 60),
+            clock_rule(0, 0),
+            clock_rule(7 * 60, 18 * 60),
+        ];
+        assert_eq!(active(&rules, local(2025, 1, 15, 12, 30)), [0, 1, 2]);
+        assert_eq!(active(&rules, local(2025, 1, 15, 20, 0)), [1]);
+    }
+
+    #[test]
+    fn sun_anchors_offset_and_clamp_the_window() {
+        let date = NaiveDate::from_ymd_opt(2025, 6, 21).unwrap();
+        let sunrise = anchor_minute(TimeAnchor::Sunrise, LONDON, date).unwrap();
+        let sunset = anchor_minute(TimeAnchor::Sunset, LONDON, date).unwrap();
+        let dawn = anchor_minute(TimeAnchor::CivilDawn, LONDON, date).unwrap();
+        assert!(dawn < sunrise && sunrise < sunset);
+
+        let rule = ScheduleRule {
+            start_anchor: TimeAnchor::Sunrise,
+            start_minute: 30,
+            end_anchor: TimeAnchor::Sunset,
+            end_minute: 3 * MINUTES_PER_DAY,
+            ..ScheduleRule::default()
+        };
+        let end = (sunset + MAX_ANCHOR_OFFSET).clamp(0, MINUTES_PER_DAY);
+        assert_eq!(
+            window(&rule, LONDON, date),
+            Some(((sunrise + 30).clamp(0, MINUTES_PER_DAY), end))
+        );
+        assert_eq!(anchor_minute(TimeAnchor::Clock, LONDON, date), None);
+    }
+
+    #[test]
+    fn sun_anchored_rule_is_skipped_without_the_event() {
+        let date = NaiveDate::from_ymd_opt(2025, 12, 21).unwrap();
+        assert_eq!(anchor_minute(TimeAnchor::Sunrise, SVALBARD, date), None);
+
+        let rule = ScheduleRule {
+            start_anchor: TimeAnchor::Sunrise,
+            start_minute: 0,
+            ..ScheduleRule::default()
+        };
+        let noon = local(2025, 12, 21, 12, 0);
+        assert!(active_rules(std::slice::from_ref(&rule), SVALBARD, noon).is_empty());
+        // No sunrise for the whole search window, so no boundary either.
+        assert_eq!(until_next_boundary(&[rule], SVALBARD, noon), None);
+    }
+
+    #[test]
+    fn next_boundary_is_the_nearest_start_or_end() {
+        let now = local(2025, 1, 15, 12, 0);
+        let rules = [clock_rule(7 * 60, 18 * 60), clock_rule(13 * 60, 14 * 60)];
+        let wait = until_next_boundary(&rules, LONDON, now).unwrap();
+        assert_eq!(wait.as_secs(), local(2025, 1, 15, 13, 0) - now);
+        assert_eq!(until_next_boundary(&[], LONDON, now), None);
+    }
+
+    #[test]
+    fn next_boundary_crosses_midnight() {
+        let now = local(2025, 1, 15, 23, 0);
+        let rules = [clock_rule(22 * 60, 6 * 60)];
+        let wait = until_next_boundary(&rules, LONDON, now).unwrap();
+        assert_eq!(wait.as_secs(), local(2025, 1, 16, 6, 0) - now);
+    }
+
+    #[test]
+    fn next_boundary_searches_ahead_past_days_without_the_event() {
+        // First sunrise after the polar night, a few days after `now`.
+        let first_sunrise = (1..90)
+            .filter_map(|day| NaiveDate::from_yo_opt(2025, day))
+            .find(|&date| anchor_minute(TimeAnchor::Sunrise, SVALBARD, date).is_some())
+            .unwrap();
+        let rule = ScheduleRule {
+            start_anchor: TimeAnchor::Sunrise,
+            start_minute: 0,
+            end_anchor: TimeAnchor::Sunrise,
+            end_minute: 60,
+            ..ScheduleRule::default()
+        };
+        let earlier = first_sunrise - chrono::Days::new(BOUNDARY_SEARCH_DAYS - 3);
+        let now = local(earlier.year(), earlier.month(), earlier.day(), 12, 0);
+        let start = window(&rule, SVALBARD, first_sunrise).unwrap().0;
+        let expected = local_timestamp(first_sunrise, start).unwrap() as u64;
+        let wait = until_next_boundary(std::slice::from_ref(&rule), SVALBARD, now).unwrap();
+        assert_eq!(wait.as_secs(), expected - now);
+
+        let too_early = first_sunrise - chrono::Days::new(BOUNDARY_SEARCH_DAYS + 1);
+        let now = local(too_early.year(), too_early.month(), too_early.day(), 12, 0);
+        assert_eq!(until_next_boundary(&[rule], SVALBARD, now), None);
+    }
+
+    #[test]
+    fn rule_images_follow_the_listed_folders() {
+        let folders = [
+            FolderSource {
+                path: PathBuf::from("/pics/day"),
+                include_subfolders: false,
+                weight: 1,
+                interval_secs: None,
+            },
+            FolderSource {
+                path: PathBuf::from("/pics/night"),
+                include_subfolders: true,
+                weight: 1,
+                interval_secs: None,
+            },
+        ];
+        let images = [
+            PathBuf::from("/pics/day/a.jpg"),
+            PathBuf::from("/pics/day/sub/b.jpg"),
+            PathBuf::from("/pics/night/c.jpg"),
+            PathBuf::from("/pics/night/sub/d.jpg"),
+        ];
+        let mut rule = ScheduleRule {
+            folders: vec!["/pics/day".into()],
+            ..ScheduleRule::default()
+        };
+        assert_eq!(rule_images(&rule, &images, &folders), [images[0].clone()]);
+        rule.folders = vec!["/pics/night".into()];
+        assert_eq!(rule_images(&rule, &images, &folders), images[2..].to_vec());
+        rule.folders = vec!["/pics/elsewhere".into()];
+        assert!(rule_images(&rule, &images, &folders).is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+//! Offline sunrise, sunset, and civil twilight times.
+//!
+//! Uses the sunrise equation with the NOAA low-precision solar coordinates, which
+//! stays within a minute or two of published tables outside the polar regions.
+
+use chrono::NaiveDate;
+
+use crate::settings::GeoLocation;
+
+/// Julian day of the Unix epoch (1970-01-01 00:00 UTC).
+const UNIX_EPOCH_JULIAN: f64 = 2_440_587.5;
+/// Julian day of the J2000 epoch (2000-01-01 12:00 UTC).
+const J2000: f64 = 2_451_545.0;
+/// Sun altitude at sunrise and sunset, allowing for refraction and the solar disc.
+pub const HORIZON_ALTITUDE: f64 = -0.833;
+/// Sun altitude that bounds civil twilight.
+pub const CIVIL_ALTITUDE: f64 = -6.0;
+/// Axial tilt of the Earth in degrees.
+const OBLIQUITY: f64 = 23.4397;
+
+/// Unix times at which the sun rises past and sets below `altitude` degrees.
+///
+/// Returns `None` when the sun stays above or below that altitude all day, as
+/// happens near the poles around the solstices.
+pub fn crossings(date: NaiveDate, location: GeoLocation, altitude: f64) -> Option<(i64, i64)> {
+    let latitude = location.latitude.clamp(-90.0, 90.0).to_radians();
+    let longitude = location.longitude.clamp(-180.0, 180.0);
+
+    // Days since J2000 at local mean solar noon of the date.
+    let noon_utc = date.signed_duration_since(NaiveDate::from_ymd_opt(1970, 1, 1)?);
+    let day = (noon_utc.num_days() as f64 + 0.5 + UNIX_EPOCH_JULIAN - J2000).round();
+    let mean_noon = day - longitude / 360.0;
+
+    let anomaly = (357.5291 + 0.985_600_28 * mean_noon)
+        .rem_euclid(360.0)
+        .to_radians();
+    let center =
+        1.9148 * anomaly.sin() + 0.02 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+    let ecliptic_longitude = (anomaly.to_degrees() + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit =
+        J2000 + mean_noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * OBLIQUITY.to_radians().sin()).asin();
+    let cos_hour_angle = (altitude.to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+    let half_day = cos_hour_angle.acos().to_degrees() / 360.0;
+
+    Some((
+        julian_to_unix(transit - half_day),
+        julian_to_unix(transit + half_day),
+    ))
+}
+
+/// Convert a Julian day into Unix seconds.
+fn julian_to_unix(julian: f64) -> i64 {
+    ((julian - UNIX_EPOCH_JULIAN) * 86_400.0).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONDON: GeoLocation = GeoLocation {
+        latitude: 51.5074,
+        longitude: -0.1278,
+    };
+    const NEW_YORK: GeoLocation = GeoLocation {
+        latitude: 40.7128,
+        longitude: -74.006,
+    };
+    const TROMSO: GeoLocation = GeoLocation {
+        latitude: 69.6492,
+        longitude: 18.9553,
+    };
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// Unix time of a UTC wall-clock time.
+    fn utc(date: NaiveDate, hour: u32, minute: u32) -> i64 {
+        date.and_hms_opt(hour, minute, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp()
+    }
+
+    /// Assert that `actual` lies within two minutes of `expected`.
+    fn assert_near(actual: i64, expected: i64) {
+        assert!(
+            (actual - expected).abs() <= 120,
+            "{actual} is {}s away from {expected}",
+            actual - expected
+        );
+    }
+
+    #[test]
+    fn matches_published_sunrise_and_sunset() {
+        // Published times: London 04:43/21:21 BST, New York 07:20/16:39 EST.
+        let midsummer = date(2025, 6, 21);
+        let (rise, set) = crossings(midsummer, LONDON, HORIZON_ALTITUDE).unwrap();
+        assert_near(rise, utc(midsummer, 3, 43));
+        assert_near(set, utc(midsummer, 20, 21));
+
+        let new_year = date(2025, 1, 1);
+        let (rise, set) = crossings(new_year, NEW_YORK, HORIZON_ALTITUDE).unwrap();
+        assert_near(rise, utc(new_year, 12, 20));
+        assert_near(set, utc(new_year, 21, 39));
+    }
+
+    #[test]
+    fn civil_twilight_brackets_the_daylight() {
+        let day = date(2025, 3, 20);
+        let (dawn, dusk) = crossings(day, LONDON, CIVIL_ALTITUDE).unwrap();
+        let (rise, set) = crossings(day, LONDON, HORIZON_ALTITUDE).unwrap();
+        assert!(dawn < rise && rise < set && set < dusk);
+    }
+
+    #[test]
+    fn polar_day_and_night_have_no_crossings() {
+        assert_eq!(crossings(date(2025, 6, 21), TROMSO, HORIZON_ALTITUDE), None);
+        assert_eq!(
+            crossings(date(2025, 12, 21), TROMSO, HORIZON_ALTITUDE),
+            None
+        );
+        // Civil twilight still happens at midwinter noon.
+        assert!(crossings(date(2025, 12, 21), TROMSO, CIVIL_ALTITUDE).is_some());
+    }
+
+    #[test]
+    fn out_of_range_coordinates_are_clamped() {
+        let wild = GeoLocation {
+            latitude: 120.0,
+            longitude: 400.0,
+        };
+        let north_pole = GeoLocation {
+            latitude: 90.0,
+            longitude: 180.0,
+        };
+        let day = date(2025, 6, 21);
+        assert_eq!(
+            crossings(day, wild, HORIZON_ALTITUDE),
+            crossings(day, north_pole, HORIZON_ALTITUDE)
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! Slideshow position persisted so an app restart resumes the countdown.
+//!
+//! The session is held in memory behind one lock and written out on every
+//! change, so the worker recording a change and the UI or IPC listener setting a
+//! pin never overwrite each other's fields.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+use anyhow::{Result, anyhow, bail};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::slideshow::selection::until_next_local_day;
+
+/// File name used under the per-user config directory.
+const SESSION_FILE: &str = "session.json";
+
+/// Wallpaper on the desktop and when the slideshow plans to replace it.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Session {
+    /// Source images of the current wallpaper.
+    pub current_images: Vec<String>,
+    /// Unix time (seconds) of the next planned slideshow change.
+    pub next_change_at: Option<u64>,
+    /// Hold on the current wallpaper, if the user pinned it.
+    pub pin: Option<Pin>,
+}
+
+impl Session {
+    /// Current wallpaper sources as paths.
+    pub fn current_paths(&self) -> Vec<PathBuf> {
+        self.current_images.iter().map(PathBuf::from).collect()
+    }
+}
+
+/// Hold that keeps the current wallpaper while the slideshow keeps running.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Pin {
+    /// Keep the wallpaper until this Unix time (seconds).
+    Until(u64),
+    /// Keep the wallpaper until the user resumes rotation.
+    Indefinitely,
+}
+
+impl Pin {
+    /// Return true while the pin still holds at `now`.
+    pub fn is_active(self, now: u64) -> bool {
+        match self {
+            Pin::Until(at) => at > now,
+            Pin::Indefinitely => true,
+        }
+    }
+
+    /// Earliest time a change may happen; `u64::MAX` for an indefinite pin.
+    pub fn release_at(self) -> u64 {
+        match self {
+            Pin::Until(at) => at,
+            Pin::Indefinitely => u64::MAX,
+        }
+    }
+}
+
+/// How long a pin request should hold, before it is resolved against the clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinRequest {
+    /// Hold for a number of seconds.
+    For(u64),
+    /// Hold until the next local midnight.
+    UntilTomorrow,
+    /// Hold until the user resumes rotation.
+    Indefinitely,
+}
+
+impl PinRequest {
+    /// Convert the request into a pin starting at `now` (Unix seconds).
+    pub fn resolve(self, now: u64) -> Pin {
+        match self {
+            PinRequest::For(secs) => Pin::Until(now.saturating_add(secs)),
+            PinRequest::UntilTomorrow => Pin::Until(now + until_next_local_day(now).as_secs()),
+            PinRequest::Indefinitely => Pin::Indefinitely,
+        }
+    }
+
+    /// Parse `tomorrow`, `forever`, or a duration such as `90s`, `30m`, `1h`, `2d`;
+    /// a bare number counts minutes.
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        match text.as_str() {
+            "tomorrow" => return Ok(PinRequest::UntilTomorrow),
+            "forever" | "indefinitely" => return Ok(PinRequest::Indefinitely),
+            _ => {}
+        }
+        let split = text
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len());
+        let (amount, unit) = text.split_at(split);
+        let amount: u64 = amount
+            .parse()
+            .map_err(|_| anyhow!("invalid pin duration `{text}`"))?;
+        let scale = match unit {
+            "s" => 1,
+            "" | "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => bail!("unknown duration unit `{unit}`; use s, m, h or d"),
+        };
+        Ok(PinRequest::For(amount.saturating_mul(scale)))
+    }
+
+    /// Text form accepted by [`PinRequest::parse`].
+    pub fn to_arg(self) -> String {
+        match self {
+            PinRequest::For(secs) => format!("{secs}s"),
+            PinRequest::UntilTomorrow => "tomorrow".to_string(),
+            PinRequest::Indefinitely => "forever".to_string(),
+        }
+    }
+}
+
+/// Session of this process, opened from the config directory on first use.
+static STORE: LazyLock<Option<SessionStore>> =
+    LazyLock::new(|| session_path().ok().map(SessionStore::open));
+
+/// A session held in memory and the file it is saved to.
+#[derive(Debug)]
+pub struct SessionStore {
+    path: PathBuf,
+    session: Mutex<Session>,
+}
+
+impl SessionStore {
+    /// Open the session saved at `path`, starting empty when it is missing or
+    /// unreadable.
+    pub fn open(path: PathBuf) -> Self {
+        let session = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            session: Mutex::new(session),
+        }
+    }
+
+    /// Copy of the current session.
+    pub fn snapshot(&self) -> Session {
+        self.lock().clone()
+    }
+
+    /// Store the current wallpaper and the next planned change.
+    ///
+    /// A pin that is still active is kept; an expired one is dropped.
+    pub fn record(&self, images: &[PathBuf], next_change_at: Option<u64>, now: u64) -> Result<()> {
+        let mut session = self.lock();
+        session.current_images = images
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        session.next_change_at = next_change_at;
+        session.pin = session.pin.filter(|pin| pin.is_active(now));
+        save(&self.path, &session)
+    }
+
+    /// Store or clear the pin without touching the rest of the session.
+    pub fn set_pin(&self, pin: Option<Pin>) -> Result<()> {
+        let mut session = self.lock();
+        session.pin = pin;
+        save(&self.path, &session)
+    }
+
+    /// Lock the session, recovering it if another thread panicked; the lock is
+    /// held until the change is written.
+    fn lock(&self) -> MutexGuard<'_, Session> {
+        self.session
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Store the current wallpaper and the next planned change in this process's
+/// session; see [`SessionStore::record`].
+pub fn record(images: &[PathBuf], next_change_at: Option<u64>, now: u64) -> Result<()> {
+    store()?.record(images, next_change_at, now)
+}
+
+/// Store or clear the pin in this process's session.
+pub fn set_pin(pin: Option<Pin>) -> Result<()> {
+    store()?.set_pin(pin)
+}
+
+/// This process's session store.
+fn store() -> Result<&'static SessionStore> {
+    STORE
+        .as_ref()
+        .ok_or_else(|| anyhow!("cannot determine config directory"))
+}
+
+/// Build the session path and ensure the directory exists.
+fn session_path() -> Result<PathBuf> {
+    let proj_dirs = ProjectDirs::from("dev", "wallpaper_manager", "wallpaper_manager")
+        .ok_or_else(|| anyhow!("cannot determine config directory"))?;
+    let config_dir = proj_dirs.config_dir();
+    fs::create_dir_all(config_dir)?;
+    Ok(config_dir.join(SESSION_FILE))
+}
+
+/// The last session, returning an empty one when missing.
+pub fn load() -> Session {
+    STORE
+        .as_ref()
+        .map(SessionStore::snapshot)
+        .unwrap_or_default()
+}
+
+/// Persist a session to `path`.
+fn save(path: &Path, session: &Session) -> Result<()> {
+    let contents = serde_json::to_string_pretty(session)?;
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn images(idx: usize) -> Vec<PathBuf> {
+        vec![PathBuf::from(format!("img{idx}.jpg"))]
+    }
+
+    #[test]
+    fn record_keeps_a_pin_set_between_changes() {
+        let dir = TempDir::new("session");
+        let store = SessionStore::open(dir.path().join(SESSION_FILE));
+        store.record(&images(0), Some(100), 10).unwrap();
+        store.set_pin(Some(Pin::Until(500))).unwrap();
+        store.record(&images(1), Some(200), 20).unwrap();
+
+        let saved = SessionStore::open(dir.path().join(SESSION_FILE)).snapshot();
+        assert_eq!(saved.pin, Some(Pin::Until(500)));
+        assert_eq!(saved.current_images, ["img1.jpg"]);
+        assert_eq!(saved.next_change_at, Some(200));
+    }
+
+    #[test]
+    fn record_drops_an_expired_pin() {
+        let dir = TempDir::new("session");
+        let store = SessionStore::open(dir.path().join(SESSION_FILE));
+        store.set_pin(Some(Pin::Until(15))).unwrap();
+        store.record(&images(0), None, 20).unwrap();
+        assert_eq!(store.snapshot().pin, None);
+    }
+
+    #[test]
+    fn concurrent_records_never_lose_a_pin() {
+        let dir = TempDir::new("session");
+        let store = Arc::new(SessionStore::open(dir.path().join(SESSION_FILE)));
+        let worker = {
+            let store = Arc::clone(&store);
+            thread::spawn(move || {
+                for idx in 0..200 {
+                    store.record(&images(idx), Some(idx as u64), 0).unwrap();
+                }
+            })
+        };
+        for _ in 0..20 {
+            store.set_pin(Some(Pin::Indefinitely)).unwrap();
+            thread::yield_now();
+        }
+        worker.join().unwrap();
+
+        let saved = SessionStore::open(dir.path().join(SESSION_FILE)).snapshot();
+        assert_eq!(saved.pin, Some(Pin::Indefinitely));
+        assert_eq!(saved.current_images, ["img199.jpg"]);
+    }
+}
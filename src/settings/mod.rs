@@ -8,6 +8,9 @@ use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 
 use crate::i18n::Language;
+use crate::image_ops::layout::StitchLayout;
+use crate::monitor::power::SYSFS_POWER_SUPPLY;
+use crate::monitor::process::PROCFS_ROOT;
 use crate::wallpaper::StyleMode;
 
 /// File name used under the per-user config directory.
@@ -20,6 +23,110 @@ pub struct FolderSetting {
     pub path: String,
     /// Whether subfolders should be scanned.
     pub include_subfolders: bool,
+    /// Relative weight when random selection picks a folder first.
+    #[serde(default = "default_folder_weight")]
+    pub weight: u32,
+    /// Seconds this folder's images stay up, overriding the slideshow interval.
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+}
+
+/// Folders saved before weights existed count as weight 1.
+fn default_folder_weight() -> u32 {
+    1
+}
+
+/// Time-of-day rule that limits the slideshow to a subset of source folders.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ScheduleRule {
+    /// Label shown in the schedule editor.
+    pub name: String,
+    /// Days on which the rule window starts, Monday first.
+    pub weekdays: [bool; 7],
+    /// What the window start is measured from.
+    pub start_anchor: TimeAnchor,
+    /// Window start in minutes after local midnight, or the offset from a sun event.
+    pub start_minute: i32,
+    /// What the window end is measured from.
+    pub end_anchor: TimeAnchor,
+    /// Window end in minutes after local midnight, or the offset from a sun event;
+    /// an end before the start wraps past midnight, and an equal end means the whole day.
+    pub end_minute: i32,
+    /// Paths of the source folders used while the rule is active.
+    pub folders: Vec<String>,
+}
+
+impl Default for ScheduleRule {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            weekdays: [true; 7],
+            start_anchor: TimeAnchor::Clock,
+            start_minute: 7 * 60,
+            end_anchor: TimeAnchor::Clock,
+            end_minute: 18 * 60,
+            folders: Vec::new(),
+        }
+    }
+}
+
+/// Reference point for a schedule rule boundary.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum TimeAnchor {
+    /// Fixed local clock time.
+    #[default]
+    Clock,
+    /// Start of civil twilight (sun 6° below the horizon, rising).
+    CivilDawn,
+    /// Sun crosses the horizon in the morning.
+    Sunrise,
+    /// Sun crosses the horizon in the evening.
+    Sunset,
+    /// End of civil twilight (sun 6° below the horizon, setting).
+    CivilDusk,
+}
+
+impl TimeAnchor {
+    /// All anchors in the order shown in the UI.
+    pub const ALL: [TimeAnchor; 5] = [
+        TimeAnchor::Clock,
+        TimeAnchor::CivilDawn,
+        TimeAnchor::Sunrise,
+        TimeAnchor::Sunset,
+        TimeAnchor::CivilDusk,
+    ];
+}
+
+/// Fixed position used to compute sun events offline.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct GeoLocation {
+    /// Degrees north of the equator; south is negative.
+    pub latitude: f64,
+    /// Degrees east of Greenwich; west is negative.
+    pub longitude: f64,
+}
+
+/// What the slideshow does while the machine runs on battery.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum BatteryPolicy {
+    /// Keep the normal timing.
+    #[default]
+    Ignore,
+    /// Multiply the interval by `battery_interval_factor`.
+    Lengthen,
+    /// Hold the current wallpaper until AC power returns.
+    Pause,
+}
+
+impl BatteryPolicy {
+    /// All policies in the order shown in the UI.
+    pub const ALL: [BatteryPolicy; 3] = [
+        BatteryPolicy::Ignore,
+        BatteryPolicy::Lengthen,
+        BatteryPolicy::Pause,
+    ];
 }
 
 /// Theme options exposed in the UI.
@@ -54,6 +161,249 @@ impl Default for StitchOrientation {
     }
 }
 
+/// Direction portrait images turn when auto-rotate makes them landscape.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum RotateDirection {
+    /// Turn a quarter clockwise.
+    #[default]
+    Clockwise,
+    /// Turn a quarter counter-clockwise.
+    CounterClockwise,
+}
+
+impl RotateDirection {
+    /// Fixed list of both directions.
+    pub const ALL: [RotateDirection; 2] = [
+        RotateDirection::Clockwise,
+        RotateDirection::CounterClockwise,
+    ];
+}
+
+/// Resampling filter used when scaling images to the screen.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum ResampleFilter {
+    /// Nearest neighbour; fastest, blocky when enlarging.
+    Nearest,
+    /// Bilinear.
+    Triangle,
+    /// Bicubic.
+    #[default]
+    CatmullRom,
+    /// Lanczos with a window of 3; sharpest and slowest.
+    Lanczos3,
+}
+
+impl ResampleFilter {
+    /// Fixed list of all filters, fastest first.
+    pub const ALL: [ResampleFilter; 4] = [
+        ResampleFilter::Nearest,
+        ResampleFilter::Triangle,
+        ResampleFilter::CatmullRom,
+        ResampleFilter::Lanczos3,
+    ];
+}
+
+/// Background used where an image leaves part of its frame uncovered.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum LetterboxFill {
+    /// A single user-chosen colour.
+    #[default]
+    Solid,
+    /// A blurred, darkened copy of the image.
+    Blur,
+    /// The image's most common colour.
+    Dominant,
+    /// The image reflected across its edges.
+    Mirror,
+}
+
+impl LetterboxFill {
+    /// Fixed list of all fills.
+    pub const ALL: [LetterboxFill; 4] = [
+        LetterboxFill::Solid,
+        LetterboxFill::Blur,
+        LetterboxFill::Dominant,
+        LetterboxFill::Mirror,
+    ];
+}
+
+/// How stitched images are sized to their layout slots.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum StitchScaling {
+    /// Native size, end to end along each line; weighted layouts centre images in
+    /// slots sized by their shares.
+    #[default]
+    Native,
+    /// Scaled to a common row height or column width, edge to edge.
+    Normalized,
+    /// Cropped to fill each slot at the output size.
+    Cover,
+}
+
+impl StitchScaling {
+    /// Fixed list of all scaling modes.
+    pub const ALL: [StitchScaling; 3] = [
+        StitchScaling::Native,
+        StitchScaling::Normalized,
+        StitchScaling::Cover,
+    ];
+}
+
+/// How a covering image is cropped down to its frame.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum CropMode {
+    /// Keep the middle of the image.
+    #[default]
+    Center,
+    /// Keep the part with the most detail.
+    Smart,
+}
+
+impl CropMode {
+    /// Fixed list of all crop modes.
+    pub const ALL: [CropMode; 2] = [CropMode::Center, CropMode::Smart];
+}
+
+/// How a display is turned, as set in the system display settings.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum DisplayRotation {
+    /// Upright, wider than tall for ordinary panels.
+    #[default]
+    Landscape,
+    /// Turned a quarter, so width and height swap.
+    Portrait,
+    /// Upside down.
+    LandscapeFlipped,
+    /// Turned three quarters, so width and height swap.
+    PortraitFlipped,
+}
+
+impl DisplayRotation {
+    /// Fixed list of all rotations.
+    pub const ALL: [DisplayRotation; 4] = [
+        DisplayRotation::Landscape,
+        DisplayRotation::Portrait,
+        DisplayRotation::LandscapeFlipped,
+        DisplayRotation::PortraitFlipped,
+    ];
+
+    /// Return true when the display's width and height swap on the desktop.
+    pub fn is_turned(&self) -> bool {
+        matches!(
+            self,
+            DisplayRotation::Portrait | DisplayRotation::PortraitFlipped
+        )
+    }
+}
+
+/// One monitor of the virtual desktop a spanned wallpaper covers.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SpanDisplay {
+    /// Left edge on the virtual desktop in pixels; may be negative.
+    pub x: i32,
+    /// Top edge on the virtual desktop in pixels; may be negative.
+    pub y: i32,
+    /// Resolution width in pixels, before rotation.
+    pub width: u32,
+    /// Resolution height in pixels, before rotation.
+    pub height: u32,
+    /// How the display is turned.
+    pub rotation: DisplayRotation,
+}
+
+impl SpanDisplay {
+    /// Size the display covers on the desktop, after rotation.
+    pub fn desktop_size(&self) -> (u32, u32) {
+        if self.rotation.is_turned() {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+}
+
+impl Default for SpanDisplay {
+    fn default() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+            rotation: DisplayRotation::Landscape,
+        }
+    }
+}
+
+/// Ordering applied to the image list when the slideshow runs sequentially.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum SortMode {
+    /// Plain path order (`img10` before `img2`).
+    #[default]
+    Name,
+    /// Numeric-aware path order (`img2` before `img10`).
+    NaturalName,
+    /// File modification time, oldest first.
+    Modified,
+    /// EXIF `DateTimeOriginal`, falling back to modification time.
+    ExifDate,
+    /// File size, smallest first.
+    Size,
+    /// One image from each source folder in turn.
+    FolderRoundRobin,
+}
+
+impl SortMode {
+    /// Fixed list of all supported sort modes.
+    pub const ALL: [SortMode; 6] = [
+        SortMode::Name,
+        SortMode::NaturalName,
+        SortMode::Modified,
+        SortMode::ExifDate,
+        SortMode::Size,
+        SortMode::FolderRoundRobin,
+    ];
+}
+
+/// How random selection draws the next image.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum RandomMode {
+    /// Every indexed image is equally likely.
+    #[default]
+    Uniform,
+    /// Pick a source folder by weight first, then an image inside it.
+    ByFolder,
+    /// Favour highly rated and rarely shown images.
+    Weighted,
+    /// Derive one pick per local calendar day from the date and seed.
+    Daily,
+}
+
+impl RandomMode {
+    /// Fixed list of all supported random modes.
+    pub const ALL: [RandomMode; 4] = [
+        RandomMode::Uniform,
+        RandomMode::ByFolder,
+        RandomMode::Weighted,
+        RandomMode::Daily,
+    ];
+}
+
+/// How the wait between slideshow changes is chosen.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum IntervalMode {
+    /// Wait exactly `interval_secs`.
+    #[default]
+    Fixed,
+    /// Draw each wait between `interval_secs` and `interval_max_secs`.
+    Random,
+}
+
+impl IntervalMode {
+    /// Fixed list of all interval modes.
+    pub const ALL: [IntervalMode; 2] = [IntervalMode::Fixed, IntervalMode::Random];
+}
+
 /// Settings persisted to `settings.json`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -70,14 +420,66 @@ pub struct AppSettings {
     pub single_image: Option<String>,
     /// Auto-rotate portrait images.
     pub auto_rotate: bool,
+    /// Which way auto-rotate turns portrait images.
+    pub rotate_direction: RotateDirection,
     /// Random vs sequential selection.
     pub random_order: bool,
+    /// Image ordering used by sequential selection.
+    pub sort_mode: SortMode,
+    /// Draw strategy used by random selection.
+    pub random_mode: RandomMode,
+    /// Random selection skips images shown within this many days (0 disables).
+    pub repeat_window_days: u32,
+    /// Random selection skips images shown within this many changes (0 disables).
+    pub repeat_window_changes: u32,
+    /// Fixed RNG seed for reproducible sequences; `None` seeds from entropy.
+    pub seed: Option<u64>,
+    /// Whether schedule rules restrict which folders are used.
+    pub schedule_enabled: bool,
+    /// Time-of-day source rules; the first active rule wins.
+    pub schedule_rules: Vec<ScheduleRule>,
+    /// Location used for sunrise and sunset anchors.
+    pub location: GeoLocation,
     /// Slideshow interval in seconds.
     pub interval_secs: u64,
+    /// Whether the interval is fixed or drawn from a range.
+    pub interval_mode: IntervalMode,
+    /// Upper bound of the random interval in seconds.
+    pub interval_max_secs: u64,
+    /// Whether changes follow `cron_expression` instead of the interval.
+    pub cron_enabled: bool,
+    /// Five-field cron expression evaluated against local time.
+    pub cron_expression: String,
+    /// Whether waking the machine from sleep changes the wallpaper right away.
+    pub change_on_resume: bool,
+    /// What the slideshow does while on battery.
+    pub battery_policy: BatteryPolicy,
+    /// Interval multiplier used by the lengthen battery policy.
+    pub battery_interval_factor: u32,
+    /// Linux `power_supply` directory read for AC/battery state.
+    pub power_supply_root: String,
+    /// Application names that hold the current wallpaper while running.
+    pub pause_apps: Vec<String>,
+    /// Linux procfs directory scanned for running applications.
+    pub proc_root: String,
     /// UI language selection.
     pub language: Language,
     /// Windows wallpaper style.
     pub style: StyleMode,
+    /// Whether the app renders the final frame itself instead of leaving scaling to the OS.
+    pub prerender: bool,
+    /// Pre-render width in pixels; 0 detects the screen size.
+    pub render_width: u32,
+    /// Pre-render height in pixels; 0 detects the screen size.
+    pub render_height: u32,
+    /// Filter used when pre-rendering scales an image.
+    pub resample_filter: ResampleFilter,
+    /// Background around Fit/Center frames and inside stitch cells.
+    pub letterbox_fill: LetterboxFill,
+    /// RGB colour used by the solid letterbox fill.
+    pub letterbox_color: [u8; 3],
+    /// How Fill frames and stitched canvases are cropped.
+    pub crop_mode: CropMode,
     /// Whether the slideshow should resume on startup.
     pub running: bool,
     /// Selected UI theme.
@@ -90,6 +492,30 @@ pub struct AppSettings {
     pub stitch_count: u8,
     /// Orientation for stitching.
     pub stitch_orientation: StitchOrientation,
+    /// Name of the selected stitch layout; empty picks one from count and orientation.
+    pub stitch_layout: String,
+    /// Layouts the user defined.
+    pub stitch_layouts: Vec<StitchLayout>,
+    /// How stitched images are sized to their slots.
+    pub stitch_scaling: StitchScaling,
+    /// Gap between stitched images in pixels.
+    pub stitch_gap: u32,
+    /// Margin around a stitched composite in pixels.
+    pub stitch_margin: u32,
+    /// RGB colour of the gaps and margin.
+    pub stitch_border_color: [u8; 3],
+    /// Corner radius of stitched images in pixels.
+    pub stitch_corner_radius: u32,
+    /// Whether stitched images cast a shadow onto the background.
+    pub stitch_shadow: bool,
+    /// Whether wallpapers are collages of many images instead.
+    pub collage_enabled: bool,
+    /// Number of images in a collage.
+    pub collage_count: u32,
+    /// Whether Span wallpapers get a separate image on every display.
+    pub span_per_display: bool,
+    /// Monitors the spanned canvas is composed for.
+    pub span_displays: Vec<SpanDisplay>,
     /// Target width for cropping (always applied when stitching).
     pub stitch_crop_width: u32,
     /// Target height for cropping (always applied when stitching).
@@ -105,16 +531,54 @@ impl Default for AppSettings {
             folders: Vec::new(),
             single_image: None,
             auto_rotate: true,
+            rotate_direction: RotateDirection::Clockwise,
             random_order: true,
+            sort_mode: SortMode::Name,
+            random_mode: RandomMode::Uniform,
+            repeat_window_days: 0,
+            repeat_window_changes: 1,
+            seed: None,
+            schedule_enabled: false,
+            schedule_rules: Vec::new(),
+            location: GeoLocation::default(),
             interval_secs: 600,
+            interval_mode: IntervalMode::Fixed,
+            interval_max_secs: 1800,
+            cron_enabled: false,
+            cron_expression: "0 * * * *".to_string(),
+            change_on_resume: false,
+            battery_policy: BatteryPolicy::Ignore,
+            battery_interval_factor: 3,
+            power_supply_root: SYSFS_POWER_SUPPLY.to_string(),
+            pause_apps: Vec::new(),
+            proc_root: PROCFS_ROOT.to_string(),
             language: Language::En,
             style: StyleMode::Fill,
+            prerender: false,
+            render_width: 0,
+            render_height: 0,
+            resample_filter: ResampleFilter::CatmullRom,
+            letterbox_fill: LetterboxFill::Solid,
+            letterbox_color: [0, 0, 0],
+            crop_mode: CropMode::Center,
             running: false,
             theme: ThemeMode::Dark,
             window_opacity: 1.0,
             stitch_enabled: false,
             stitch_count: 3,
             stitch_orientation: StitchOrientation::Horizontal,
+            stitch_layout: String::new(),
+            stitch_layouts: Vec::new(),
+            stitch_scaling: StitchScaling::Native,
+            stitch_gap: 0,
+            stitch_margin: 0,
+            stitch_border_color: [0, 0, 0],
+            stitch_corner_radius: 0,
+            stitch_shadow: false,
+            collage_enabled: false,
+            collage_count: 16,
+            span_per_display: false,
+            span_displays: Vec::new(),
             stitch_crop_width: 5120,
             stitch_crop_height: 1440,
         }
@@ -141,7 +605,12 @@ pub fn load() -> AppSettings {
         Ok(contents) => contents,
         Err(_) => return AppSettings::default(),
     };
-    serde_json::from_str(&contents).unwrap_or_default()
+    let mut settings: AppSettings = serde_json::from_str(&contents).unwrap_or_default();
+    // Hand-edited layouts get the editor's checks; bad weights break the slot math.
+    settings
+        .stitch_layouts
+        .retain(|layout| layout.validate().is_ok());
+    settings
 }
 
 /// Persist settings to disk as pretty JSON.
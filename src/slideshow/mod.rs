@@ -1,26 +1,72 @@
 //! Background slideshow worker and image selection logic.
 
+pub mod preview;
+pub mod selection;
+
 use std::path::PathBuf;
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use anyhow::Result;
-use rand::SeedableRng;
-use rand::seq::SliceRandom;
-use rand_chacha::ChaChaRng;
+use rand::Rng;
 
-use crate::image_ops::{process_image, stitch_images};
-use crate::settings::StitchOrientation;
+use crate::image_ops::collage::collage_images;
+use crate::image_ops::decor::StitchStyle;
+use crate::image_ops::layout::StitchLayout;
+use crate::image_ops::render::{Letterbox, RenderTarget};
+use crate::image_ops::span::{SpanOptions, span_images};
+use crate::image_ops::{FolderSource, StitchOptions, process_image, stitch_images};
+use crate::library::{self, RepeatWindow, SharedLibrary};
+use crate::monitor::{
+    self,
+    power::{PowerMonitor, PowerSource},
+    process::ProcessMonitor,
+};
+use crate::schedule::cron::CronSchedule;
+use crate::session::{self, Pin};
+use crate::settings::{
+    BatteryPolicy, CropMode, GeoLocation, RandomMode, ResampleFilter, RotateDirection,
+    ScheduleRule, SpanDisplay, StitchScaling,
+};
 use crate::wallpaper::{StyleMode, set_wallpaper, set_wallpaper_style};
 
+pub use selection::{ImageSelector, make_rng, make_timing_rng};
+
+/// Longest single sleep, so wall-clock jumps are noticed within a minute.
+const MAX_TICK: Duration = Duration::from_secs(60);
+/// Extra wall-clock seconds in one tick that count as a resume from sleep.
+const RESUME_SLACK_SECS: u64 = 30;
+/// How often the power source is checked while a battery policy is active.
+const POWER_POLL: Duration = Duration::from_secs(15);
+/// How often running applications are checked while a pause list is set.
+const PROCESS_POLL: Duration = Duration::from_secs(5);
+
 /// Command messages sent to the slideshow worker.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum SlideshowCommand {
     /// Stop the worker loop.
     Stop,
-    /// Advance to the next image immediately.
+    /// Advance to the next image immediately, clearing any pin.
     Next,
+    /// Hold or release the current wallpaper.
+    Pin(Option<Pin>),
+    /// The machine switched between AC and battery.
+    Power(PowerSource),
+    /// A listed application started (`Some`) or every listed one exited (`None`).
+    Blocked(Option<String>),
+    /// Watch a new list of applications instead.
+    PauseApps(ProcessMonitor),
+}
+
+/// Why the worker is holding the current wallpaper on its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PauseReason {
+    /// The battery policy pauses changes while unplugged.
+    OnBattery,
+    /// A listed application is running.
+    AppRunning(String),
 }
 
 /// Events emitted by the worker to inform the UI.
@@ -28,10 +74,164 @@ pub enum SlideshowCommand {
 pub enum SlideshowEvent {
     /// Informational status text (last image set).
     Info(String),
+    /// Source images of the wallpaper that was just applied.
+    Applied(Vec<PathBuf>),
+    /// Unix time (seconds) of the next planned change.
+    Scheduled(u64),
+    /// The worker paused (`Some`) or resumed (`None`) rotation by itself.
+    Paused(Option<PauseReason>),
     /// Terminal error that stops the worker.
     Error(String),
 }
 
+/// Everything the worker needs to select, render, and time wallpapers.
+#[derive(Debug, Clone)]
+pub struct SlideshowConfig {
+    /// Indexed images, already sorted for sequential playback.
+    pub images: Vec<PathBuf>,
+    /// Source folders used to group images for folder-aware selection.
+    pub folders: Vec<FolderSource>,
+    /// Direction to turn portrait images to landscape; `None` leaves them upright.
+    pub auto_rotate: Option<RotateDirection>,
+    /// Windows wallpaper style applied before the first change.
+    pub style: StyleMode,
+    /// Frame to pre-render each wallpaper into; `None` leaves scaling to the OS.
+    pub render: Option<RenderTarget>,
+    /// Delay between wallpaper changes.
+    pub interval: Duration,
+    /// When set, each delay is drawn between `interval` and this bound instead.
+    pub interval_max: Option<Duration>,
+    /// Wall-clock change times that replace `interval` when set.
+    pub cron: Option<CronSchedule>,
+    /// Whether waking the machine from sleep changes the wallpaper right away.
+    pub change_on_resume: bool,
+    /// Deadline carried over from the previous run; the first change waits for it.
+    pub resume_at: Option<u64>,
+    /// Images on the desktop when the worker starts; sequential order continues after them.
+    pub current_images: Vec<PathBuf>,
+    /// Hold on the current wallpaper carried over from the UI or the previous run.
+    pub pin: Option<Pin>,
+    /// What to do while the machine runs on battery.
+    pub battery_policy: BatteryPolicy,
+    /// Interval multiplier used by [`BatteryPolicy::Lengthen`].
+    pub battery_interval_factor: u32,
+    /// Source of AC/battery readings.
+    pub power: PowerMonitor,
+    /// Applications that hold the current wallpaper while running.
+    pub processes: ProcessMonitor,
+    /// Whether to choose images randomly instead of sequential order.
+    pub random_order: bool,
+    /// Draw strategy used by random selection.
+    pub random_mode: RandomMode,
+    /// How long shown images stay excluded from random selection.
+    pub repeat_window: RepeatWindow,
+    /// Fixed RNG seed for reproducible sequences; `None` seeds from entropy.
+    pub seed: Option<u64>,
+    /// Time-of-day source rules; empty when scheduling is disabled.
+    pub schedule: Vec<ScheduleRule>,
+    /// Location that resolves sunrise and sunset anchors in `schedule`.
+    pub location: GeoLocation,
+    /// Whether to stitch multiple images together.
+    pub stitch_enabled: bool,
+    /// Slots of a stitched wallpaper; its slot count is the batch size.
+    pub stitch_layout: StitchLayout,
+    /// How stitched images are sized to their slots.
+    pub stitch_scaling: StitchScaling,
+    /// Gaps, margin and corners between stitched images.
+    pub stitch_style: StitchStyle,
+    /// Images per collage when collage mode is on; takes precedence over stitching.
+    pub collage: Option<usize>,
+    /// Displays that each get their own image of a Span wallpaper; takes precedence
+    /// over collages and stitching.
+    pub span_displays: Option<Vec<SpanDisplay>>,
+    /// Target width for cropping stitched output.
+    pub stitch_crop_width: u32,
+    /// Target height for cropping stitched output.
+    pub stitch_crop_height: u32,
+    /// Background for uncovered areas of stitch cells and pre-rendered frames.
+    pub letterbox: Letterbox,
+    /// How stitched output and pre-rendered Fill frames are cropped.
+    pub crop_mode: CropMode,
+    /// Ratings and show counts used by weighted selection.
+    pub library: SharedLibrary,
+}
+
+impl SlideshowConfig {
+    /// Build a selector that follows this configuration's ordering rules.
+    pub fn selector(&self) -> ImageSelector {
+        ImageSelector::new(self)
+    }
+
+    /// Layout used when stitching a batch; stitched output is always cropped.
+    pub fn stitch_options(&self) -> StitchOptions {
+        StitchOptions {
+            layout: self.stitch_layout.clone(),
+            crop: Some((self.stitch_crop_width, self.stitch_crop_height)),
+            crop_mode: self.crop_mode,
+            scaling: self.stitch_scaling,
+            style: self.stitch_style,
+            letterbox: self.letterbox,
+        }
+    }
+
+    /// How images are cut to their displays when spanning one per display.
+    pub fn span_options(&self) -> SpanOptions {
+        SpanOptions {
+            auto_rotate: self.auto_rotate,
+            crop_mode: self.crop_mode,
+            // Without pre-rendering there is no filter choice; use the sharpest.
+            filter: self
+                .render
+                .map_or(ResampleFilter::Lanczos3, |target| target.filter),
+            background: self.letterbox.color,
+        }
+    }
+
+    /// Number of images that make up one wallpaper.
+    pub fn batch_size(&self) -> usize {
+        if let Some(displays) = &self.span_displays {
+            displays.len().min(self.images.len())
+        } else if let Some(count) = self.collage {
+            count.min(self.images.len())
+        } else if self.stitch_enabled {
+            self.stitch_layout.slot_count().min(self.images.len())
+        } else {
+            1
+        }
+    }
+}
+
+/// Environment readings that can pause or slow the slideshow.
+#[derive(Debug, Clone)]
+struct Conditions {
+    power: PowerSource,
+    /// First listed application found running.
+    blocking_app: Option<String>,
+}
+
+/// Applications watched, shared with the polling thread so the list can change.
+type SharedProcesses = Arc<Mutex<ProcessMonitor>>;
+
+impl Conditions {
+    /// Reason to hold the current wallpaper under these conditions, if any.
+    fn pause_reason(&self, config: &SlideshowConfig) -> Option<PauseReason> {
+        if let Some(app) = &self.blocking_app {
+            return Some(PauseReason::AppRunning(app.clone()));
+        }
+        (self.power == PowerSource::Battery && config.battery_policy == BatteryPolicy::Pause)
+            .then_some(PauseReason::OnBattery)
+    }
+
+    /// Multiplier applied to the fixed interval.
+    fn interval_factor(&self, config: &SlideshowConfig) -> u64 {
+        if self.power == PowerSource::Battery && config.battery_policy == BatteryPolicy::Lengthen {
+            config.battery_interval_factor.max(1) as u64
+        } else {
+            1
+        }
+    }
+}
+
 /// Handle to a background slideshow worker thread.
 #[derive(Debug)]
 pub struct SlideshowWorker {
@@ -42,37 +242,18 @@ pub struct SlideshowWorker {
 
 impl SlideshowWorker {
     /// Spawn a slideshow worker and return a handle for control/event polling.
-    pub fn start(
-        images: Vec<PathBuf>,
-        auto_rotate: bool,
-        style: StyleMode,
-        interval: Duration,
-        random_order: bool,
-        stitch_enabled: bool,
-        stitch_count: u8,
-        stitch_orientation: StitchOrientation,
-        stitch_crop_width: u32,
-        stitch_crop_height: u32,
-    ) -> Result<Self> {
-        set_wallpaper_style(style)?;
+    pub fn start(config: SlideshowConfig) -> Result<Self> {
+        set_wallpaper_style(config.style)?;
 
         let (cmd_tx, cmd_rx) = mpsc::channel();
         let (evt_tx, evt_rx) = mpsc::channel();
 
+        // Watchers stop polling once the worker thread drops this token.
+        let alive = Arc::new(());
+        let (conditions, processes) = start_watchers(&config, &cmd_tx, Arc::downgrade(&alive));
         let handle = thread::spawn(move || {
-            let _ = run_worker(
-                images,
-                auto_rotate,
-                interval,
-                random_order,
-                stitch_enabled,
-                stitch_count,
-                stitch_orientation,
-                stitch_crop_width,
-                stitch_crop_height,
-                cmd_rx,
-                evt_tx,
-            );
+            let _alive = alive;
+            let _ = run_worker(config, conditions, processes, cmd_rx, evt_tx);
         });
 
         Ok(Self {
@@ -87,6 +268,16 @@ impl SlideshowWorker {
         let _ = self.cmd_tx.send(SlideshowCommand::Next);
     }
 
+    /// Hold the current wallpaper, or release the hold with `None`.
+    pub fn set_pin(&self, pin: Option<Pin>) {
+        let _ = self.cmd_tx.send(SlideshowCommand::Pin(pin));
+    }
+
+    /// Watch `processes` for the pause-while-running list from now on.
+    pub fn set_pause_apps(&self, processes: ProcessMonitor) {
+        let _ = self.cmd_tx.send(SlideshowCommand::PauseApps(processes));
+    }
+
     /// Stop the worker thread without blocking the UI thread.
     pub fn stop(mut self) {
         let _ = self.cmd_tx.send(SlideshowCommand::Stop);
@@ -105,81 +296,166 @@ impl SlideshowWorker {
     }
 }
 
+/// Take the initial environment readings and start polling for changes.
+///
+/// Also returns the application list the process watcher polls.
+fn start_watchers(
+    config: &SlideshowConfig,
+    cmd_tx: &Sender<SlideshowCommand>,
+    owner: Weak<()>,
+) -> (Conditions, SharedProcesses) {
+    let mut conditions = Conditions {
+        power: PowerSource::Ac,
+        blocking_app: config.processes.read(),
+    };
+    if config.battery_policy != BatteryPolicy::Ignore {
+        conditions.power = config.power.read();
+        let power = config.power.clone();
+        let tx = cmd_tx.clone();
+        monitor::watch(
+            owner.clone(),
+            POWER_POLL,
+            conditions.power,
+            move || power.read(),
+            move |source| tx.send(SlideshowCommand::Power(*source)).is_ok(),
+        );
+    }
+    // Polled even while the list is empty, since the UI can fill it in later.
+    let processes = Arc::new(Mutex::new(config.processes.clone()));
+    let watched = Arc::clone(&processes);
+    let tx = cmd_tx.clone();
+    monitor::watch(
+        owner,
+        PROCESS_POLL,
+        conditions.blocking_app.clone(),
+        move || lock_processes(&watched).read(),
+        move |app| tx.send(SlideshowCommand::Blocked(app.clone())).is_ok(),
+    );
+    (conditions, processes)
+}
+
+/// Lock the watched application list, recovering it if another thread panicked.
+fn lock_processes(processes: &Mutex<ProcessMonitor>) -> MutexGuard<'_, ProcessMonitor> {
+    processes
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
 /// Main worker loop that processes images and applies wallpapers.
 fn run_worker(
-    images: Vec<PathBuf>,
-    auto_rotate: bool,
-    interval: Duration,
-    random_order: bool,
-    stitch_enabled: bool,
-    stitch_count: u8,
-    stitch_orientation: StitchOrientation,
-    stitch_crop_width: u32,
-    stitch_crop_height: u32,
+    config: SlideshowConfig,
+    mut conditions: Conditions,
+    watched_apps: SharedProcesses,
     cmd_rx: Receiver<SlideshowCommand>,
     evt_tx: Sender<SlideshowEvent>,
 ) -> Result<()> {
-    if images.is_empty() {
+    if config.images.is_empty() {
         let _ = evt_tx.send(SlideshowEvent::Error("No images selected".to_string()));
         return Ok(());
     }
 
-    let mut last: Option<PathBuf> = None;
-    let mut rng = ChaChaRng::from_entropy();
-    let mut skip_wait = false;
+    let mut selector = config.selector();
+    let mut rng = make_rng(config.seed);
+    // Intervals draw from their own stream so a seeded run matches its preview.
+    let mut timing_rng = make_timing_rng(config.seed);
+    let mut pin = config.pin;
+    let mut paused = conditions.pause_reason(&config);
+    if paused.is_some() {
+        let _ = evt_tx.send(SlideshowEvent::Paused(paused.clone()));
+    }
+
+    // Keep the wallpaper from the previous run until its planned change time.
+    let now = library::unix_now();
+    selector.refresh_schedule(now);
+    let mut last_change_at = None;
+    let mut interval = config.interval;
+    let mut next_change_at = match config.resume_at.filter(|&at| at > now) {
+        Some(resume_at) => {
+            let _ = evt_tx.send(SlideshowEvent::Scheduled(resume_at));
+            resume_at
+        }
+        None => now,
+    };
 
     loop {
-        while let Ok(cmd) = cmd_rx.try_recv() {
-            match cmd {
-                SlideshowCommand::Stop => return Ok(()),
-                SlideshowCommand::Next => {
-                    // Skip the next sleep so the slideshow advances immediately.
-                    skip_wait = true;
+        // A pin pushes the change back to its release time, never earlier.
+        let deadline = match paused {
+            Some(_) => u64::MAX,
+            None => pin.map_or(next_change_at, |pin| pin.release_at().max(next_change_at)),
+        };
+        match wait_for_change(&cmd_rx, &mut selector, deadline, config.change_on_resume) {
+            Some(SlideshowCommand::Stop) => break,
+            Some(SlideshowCommand::Pin(new_pin)) => {
+                pin = new_pin;
+                continue;
+            }
+            Some(SlideshowCommand::Power(source)) => {
+                conditions.power = source;
+                // A longer or shorter interval counts from the last change.
+                if let Some(changed_at) = last_change_at {
+                    next_change_at =
+                        plan_next_change(&config, &selector, &conditions, interval, changed_at);
+                }
+                update_pause(&config, &conditions, &mut paused, next_change_at, &evt_tx);
+                continue;
+            }
+            Some(SlideshowCommand::Blocked(app)) => {
+                conditions.blocking_app = app;
+                update_pause(&config, &conditions, &mut paused, next_change_at, &evt_tx);
+                continue;
+            }
+            Some(SlideshowCommand::PauseApps(processes)) => {
+                conditions.blocking_app = processes.read();
+                *lock_processes(&watched_apps) = processes;
+                update_pause(&config, &conditions, &mut paused, next_change_at, &evt_tx);
+                continue;
+            }
+            // An explicit request overrides any pin or pause.
+            Some(SlideshowCommand::Next) => pin = None,
+            None => {
+                // Schedule boundaries and resume from sleep wait out a pause or pin.
+                let pinned = pin.is_some_and(|pin| pin.is_active(library::unix_now()));
+                if paused.is_some() || pinned {
+                    continue;
                 }
             }
         }
 
-        let processed = if stitch_enabled {
-            let count = (stitch_count as usize).min(images.len());
-            let mut selected = Vec::with_capacity(count);
-            for _ in 0..count {
-                let next = if random_order {
-                    pick_random_with_rng(&images, last.as_ref(), &mut rng)?
-                } else {
-                    sequential_pick(&images, last.as_ref())
-                };
-                last = Some(next.clone());
-                selected.push(next);
-            }
-            let status_msg = selected
-                .iter()
-                .map(|p| {
-                    p.file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string()
-                })
-                .collect::<Vec<_>>()
-                .join(" + ");
+        let selected = selector.pick_batch(
+            config.batch_size(),
+            &mut rng,
+            &library::lock(&config.library),
+            library::unix_now(),
+        )?;
+
+        let processed = if let Some(displays) = &config.span_displays {
+            let result = span_images(&selected, displays, &config.span_options())?;
+            let _ = evt_tx.send(SlideshowEvent::Info(format!(
+                "Spanned: {}",
+                file_names(&selected)
+            )));
+            result
+        } else if config.collage.is_some() && selected.len() > 1 {
+            let result = collage_images(&selected, &config.stitch_options(), config.render)?;
+            let _ = evt_tx.send(SlideshowEvent::Info(format!(
+                "Collage: {} images",
+                selected.len()
+            )));
+            result
+        } else if config.stitch_enabled {
+            let status_msg = file_names(&selected);
             let result = stitch_images(
                 &selected,
-                auto_rotate,
-                stitch_orientation,
-                true,
-                stitch_crop_width,
-                stitch_crop_height,
+                config.auto_rotate,
+                &config.stitch_options(),
+                config.render,
             )?;
             let _ = evt_tx.send(SlideshowEvent::Info(format!("Stitched: {}", status_msg)));
             result
         } else {
-            let next = if random_order {
-                pick_random_with_rng(&images, last.as_ref(), &mut rng)?
-            } else {
-                sequential_pick(&images, last.as_ref())
-            };
-            let result = process_image(&next, auto_rotate)?;
+            let next = &selected[0];
+            let result = process_image(next, config.auto_rotate, config.render)?;
             let _ = evt_tx.send(SlideshowEvent::Info(format!("Set: {}", next.display())));
-            last = Some(next);
             result
         };
 
@@ -187,75 +463,139 @@ fn run_worker(
             let _ = evt_tx.send(SlideshowEvent::Error(err.to_string()));
             break;
         }
+        // Statistics are best effort; a failed write must not stop the slideshow.
+        let _ = library::record_change(&config.library, &selected);
 
-        if skip_wait {
-            skip_wait = false;
-            continue;
-        }
-
-        // Allow Next/Stop commands to interrupt the sleep interval.
-        match wait_or_command(&cmd_rx, interval) {
-            Some(SlideshowCommand::Stop) => break,
-            Some(SlideshowCommand::Next) => continue,
-            None => {}
-        }
+        let now = library::unix_now();
+        last_change_at = Some(now);
+        interval = choose_interval(&config, &selected, &mut timing_rng);
+        next_change_at = plan_next_change(&config, &selector, &conditions, interval, now);
+        let _ = session::record(&selected, Some(next_change_at), now);
+        let _ = evt_tx.send(SlideshowEvent::Applied(selected));
+        let _ = evt_tx.send(SlideshowEvent::Scheduled(next_change_at));
     }
 
     Ok(())
 }
 
-/// Block until the interval elapses or a command arrives.
-fn wait_or_command(
-    cmd_rx: &Receiver<SlideshowCommand>,
-    interval: Duration,
-) -> Option<SlideshowCommand> {
-    let step = Duration::from_millis(500);
-    let start = Instant::now();
-    while start.elapsed() < interval {
-        match cmd_rx.try_recv() {
-            // Stop or advance immediately when requested.
-            Ok(SlideshowCommand::Stop) => return Some(SlideshowCommand::Stop),
-            Ok(SlideshowCommand::Next) => return Some(SlideshowCommand::Next),
-            Err(_) => {}
-        }
-        let remaining = interval.saturating_sub(start.elapsed());
-        thread::sleep(step.min(remaining));
+/// File names of `paths` joined for a status message.
+fn file_names(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| {
+            p.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// Re-evaluate the pause after `conditions` changed and tell the UI what it means.
+fn update_pause(
+    config: &SlideshowConfig,
+    conditions: &Conditions,
+    paused: &mut Option<PauseReason>,
+    next_change_at: u64,
+    evt_tx: &Sender<SlideshowEvent>,
+) {
+    let reason = conditions.pause_reason(config);
+    if reason != *paused {
+        *paused = reason;
+        let _ = evt_tx.send(SlideshowEvent::Paused(paused.clone()));
+    }
+    if paused.is_none() {
+        let _ = evt_tx.send(SlideshowEvent::Scheduled(next_change_at));
     }
-    None
 }
 
-/// Select the next image in order, wrapping at the end.
-fn sequential_pick(images: &[PathBuf], last: Option<&PathBuf>) -> PathBuf {
-    if images.is_empty() {
-        return PathBuf::new();
+/// How long `shown` should stay up before the next interval-based change.
+///
+/// A source folder's own interval wins over the slideshow interval; when stitched
+/// images come from several such folders, the longest one applies.
+fn choose_interval(config: &SlideshowConfig, shown: &[PathBuf], rng: &mut impl Rng) -> Duration {
+    let folder_interval = shown
+        .iter()
+        .filter_map(|path| {
+            config
+                .folders
+                .iter()
+                .find(|folder| folder.contains(path))
+                .and_then(|folder| folder.interval_secs)
+        })
+        .max();
+    if let Some(secs) = folder_interval {
+        return Duration::from_secs(secs);
     }
-    if let Some(last) = last {
-        if let Some(pos) = images.iter().position(|p| p == last) {
-            return images[(pos + 1) % images.len()].clone();
+    match config.interval_max {
+        Some(max) if max > config.interval => {
+            Duration::from_secs(rng.gen_range(config.interval.as_secs()..=max.as_secs()))
         }
+        _ => config.interval,
     }
-    images[0].clone()
 }
 
-/// Select a random image, avoiding repeats when possible.
-fn pick_random_with_rng(
-    images: &[PathBuf],
-    last: Option<&PathBuf>,
-    rng: &mut ChaChaRng,
-) -> Result<PathBuf> {
-    if images.len() == 1 {
-        return Ok(images[0].clone());
+/// Wall-clock time (Unix seconds) of the change after one made at `now`, waiting
+/// `interval` unless a cron schedule or daily pick decides instead.
+///
+/// Only the interval is stretched on battery; cron and daily times stay put.
+fn plan_next_change(
+    config: &SlideshowConfig,
+    selector: &ImageSelector,
+    conditions: &Conditions,
+    interval: Duration,
+    now: u64,
+) -> u64 {
+    // The daily pick only changes at local midnight, so wait until then.
+    if selector.is_daily() {
+        return now + selection::until_next_local_day(now).as_secs();
     }
-    for _ in 0..5 {
-        let candidate = images
-            .choose(rng)
-            .ok_or_else(|| anyhow::anyhow!("no images available"))?;
-        if Some(candidate) != last {
-            return Ok(candidate.clone());
+    let interval = interval.as_secs().max(1) * conditions.interval_factor(config);
+    config
+        .cron
+        .as_ref()
+        .and_then(|cron| cron.next_after(now))
+        .unwrap_or(now + interval)
+}
+
+/// Block until `deadline` (Unix seconds) passes, a command arrives, a schedule
+/// boundary switches sources, or the machine resumes with `change_on_resume` set.
+///
+/// Sleeping is measured against the wall clock in ticks of at most [`MAX_TICK`], so
+/// time spent suspended counts towards the deadline. A tick that took much longer
+/// on the wall clock than requested means the machine slept through it.
+fn wait_for_change(
+    cmd_rx: &Receiver<SlideshowCommand>,
+    selector: &mut ImageSelector,
+    deadline: u64,
+    change_on_resume: bool,
+) -> Option<SlideshowCommand> {
+    loop {
+        let before = library::unix_now();
+        if before >= deadline {
+            return None;
+        }
+        let mut timeout = Duration::from_secs(deadline - before).min(MAX_TICK);
+        if let Some(boundary) = selector.until_next_boundary(before) {
+            timeout = timeout.min(boundary);
+        }
+
+        match cmd_rx.recv_timeout(timeout) {
+            Ok(cmd) => return Some(cmd),
+            // The UI dropped its handle; nobody is left to stop us.
+            Err(RecvTimeoutError::Disconnected) => return Some(SlideshowCommand::Stop),
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+
+        let after = library::unix_now();
+        let resumed = after.saturating_sub(before) > timeout.as_secs() + RESUME_SLACK_SECS;
+        if resumed && change_on_resume {
+            return None;
+        }
+        // Crossing into a different rule changes the wallpaper right away.
+        if selector.refresh_schedule(after) {
+            return None;
         }
     }
-    images
-        .choose(rng)
-        .cloned()
-        .ok_or_else(|| anyhow::anyhow!("no images available"))
 }
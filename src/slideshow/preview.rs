@@ -0,0 +1,214 @@
+//! Dry-run simulation of upcoming slideshow changes.
+//!
+//! Runs the worker's selection and timing rules forward on a simulated clock without
+//! setting wallpapers or saving statistics.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{Local, TimeZone};
+
+use super::{
+    Conditions, SlideshowConfig, choose_interval, make_rng, make_timing_rng, plan_next_change,
+};
+use crate::image_ops::{collect_images, sort_images};
+use crate::library;
+use crate::monitor::power::PowerSource;
+use crate::session;
+use crate::settings;
+use crate::state::AppState;
+
+/// Changes listed when `--preview` is given without a count.
+pub const DEFAULT_PREVIEW_COUNT: usize = 10;
+/// Most changes a single preview simulates.
+pub const MAX_PREVIEW_COUNT: usize = 1000;
+
+/// One simulated wallpaper change.
+#[derive(Debug, Clone)]
+pub struct PlannedChange {
+    /// Unix time (seconds) of the change.
+    pub at: u64,
+    /// Source images of the wallpaper, more than one when stitching.
+    pub images: Vec<PathBuf>,
+}
+
+/// Simulate the next `count` changes after `now` (Unix seconds).
+///
+/// The first change waits for `config.resume_at` and any pin, as the worker would.
+/// The simulation assumes AC power and no pausing applications, and it follows the
+/// worker's choices exactly only when `config.seed` is set. An indefinite pin yields
+/// no changes.
+pub fn simulate(config: &SlideshowConfig, count: usize, now: u64) -> Result<Vec<PlannedChange>> {
+    if config.images.is_empty() {
+        bail!("No images selected");
+    }
+    let mut at = config.resume_at.filter(|&at| at > now).unwrap_or(now);
+    if let Some(pin) = config.pin.filter(|pin| pin.is_active(now)) {
+        at = at.max(pin.release_at());
+    }
+
+    let mut selector = config.selector();
+    let mut rng = make_rng(config.seed);
+    let mut timing_rng = make_timing_rng(config.seed);
+    // Show counts evolve as in a real run, but only in this copy.
+    let mut library = library::lock(&config.library).clone();
+    let conditions = Conditions {
+        power: PowerSource::Ac,
+        blocking_app: None,
+    };
+
+    selector.refresh_schedule(now);
+    let mut changes = Vec::with_capacity(count.min(MAX_PREVIEW_COUNT));
+    while changes.len() < count.min(MAX_PREVIEW_COUNT) && at != u64::MAX {
+        let images = selector.pick_batch(config.batch_size(), &mut rng, &library, at)?;
+        library.record_change(&images, at);
+        let interval = choose_interval(config, &images, &mut timing_rng);
+        let mut next = plan_next_change(config, &selector, &conditions, interval, at);
+
+        // Crossing into a different schedule rule changes the wallpaper early.
+        let mut clock = at;
+        while let Some(wait) = selector.until_next_boundary(clock) {
+            clock += wait.as_secs().max(1);
+            if clock >= next {
+                break;
+            }
+            if selector.refresh_schedule(clock) {
+                next = clock;
+                break;
+            }
+        }
+
+        changes.push(PlannedChange { at, images });
+        at = next;
+    }
+    Ok(changes)
+}
+
+/// Read the change count from CLI arguments: `--preview [count]`.
+pub fn count_from_args(args: &[String]) -> Result<Option<usize>> {
+    let Some(idx) = args.iter().position(|arg| arg == "--preview") else {
+        return Ok(None);
+    };
+    match args.get(idx + 1).filter(|arg| !arg.starts_with("--")) {
+        Some(count) => count
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow!("--preview expects a number of changes, got `{count}`")),
+        None => Ok(Some(DEFAULT_PREVIEW_COUNT)),
+    }
+}
+
+/// Print the next `count` changes for the saved settings.
+pub fn run_cli(count: usize) -> Result<()> {
+    let settings = settings::load();
+    let state = AppState::from_settings(&settings);
+    let mut images = collect_images(&state.folders, state.single_image.as_deref())?;
+    sort_images(&mut images, &state.folders, state.sort_mode);
+    let session = session::load();
+
+    let mut config = state.slideshow_config(
+        images,
+        session.current_paths(),
+        session.pin,
+        Arc::new(Mutex::new(library::load())),
+    );
+    config.cron = state.cron_schedule()?;
+    // A running slideshow resumes its saved countdown on the next launch.
+    config.resume_at = session.next_change_at.filter(|_| settings.running);
+
+    for change in simulate(&config, count, library::unix_now())? {
+        println!(
+            "{}  {}",
+            format_time(change.at),
+            format_images(&change.images)
+        );
+    }
+    Ok(())
+}
+
+/// Local date and time of a planned change.
+pub fn format_time(at: u64) -> String {
+    Local
+        .timestamp_opt(at as i64, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| at.to_string())
+}
+
+/// Source images of a planned change, joined the way stitched status lines are.
+pub fn format_images(images: &[PathBuf]) -> String {
+    images
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::ImageLibrary;
+    use crate::settings::{AppSettings, IntervalMode};
+
+    /// Seeded random slideshow over `count` fake images with 60–120 s intervals.
+    fn seeded_config(seed: u64, count: usize) -> SlideshowConfig {
+        let mut state = AppState::from_settings(&AppSettings::default());
+        state.random_order = true;
+        state.seed = Some(seed);
+        state.interval_secs = 60;
+        state.interval_mode = IntervalMode::Random;
+        state.interval_max_secs = 120;
+        let images = (0..count)
+            .map(|idx| PathBuf::from(format!("img{idx}.jpg")))
+            .collect();
+        state.slideshow_config(
+            images,
+            Vec::new(),
+            None,
+            Arc::new(Mutex::new(ImageLibrary::default())),
+        )
+    }
+
+    fn names(changes: &[PlannedChange]) -> Vec<String> {
+        changes
+            .iter()
+            .map(|change| format_images(&change.images))
+            .collect()
+    }
+
+    #[test]
+    fn simulate_with_seed_gives_exact_sequence() {
+        let changes = simulate(&seeded_config(42, 8), 6, 1_000_000).unwrap();
+        assert_eq!(
+            names(&changes),
+            [
+                "img4.jpg", "img5.jpg", "img2.jpg", "img0.jpg", "img1.jpg", "img6.jpg"
+            ]
+        );
+        let times: Vec<u64> = changes.iter().map(|change| change.at).collect();
+        assert_eq!(
+            times,
+            [
+                1_000_000, 1_000_105, 1_000_214, 1_000_320, 1_000_415, 1_000_528
+            ]
+        );
+    }
+
+    #[test]
+    fn simulate_repeats_for_the_same_seed_only() {
+        let first = simulate(&seeded_config(7, 20), 10, 1_000_000).unwrap();
+        let again = simulate(&seeded_config(7, 20), 10, 1_000_000).unwrap();
+        let other = simulate(&seeded_config(8, 20), 10, 1_000_000).unwrap();
+        assert_eq!(names(&first), names(&again));
+        assert_ne!(names(&first), names(&other));
+    }
+
+    #[test]
+    fn simulate_waits_for_resumed_deadline() {
+        let mut config = seeded_config(42, 8);
+        config.resume_at = Some(1_000_500);
+        let changes = simulate(&config, 2, 1_000_000).unwrap();
+        assert_eq!(changes[0].at, 1_000_500);
+    }
+}
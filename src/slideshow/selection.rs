@@ -0,0 +1,341 @@
+//! Image selection strategies shared by the worker and one-off actions.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use chrono::{Datelike, Local, TimeZone};
+use rand::distributions::WeightedIndex;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+use super::SlideshowConfig;
+use crate::image_ops::FolderSource;
+use crate::library::{ImageLibrary, RepeatWindow};
+use crate::schedule;
+use crate::settings::{GeoLocation, RandomMode, ScheduleRule};
+
+/// Fallback wait when the next local midnight cannot be resolved.
+const ONE_DAY: Duration = Duration::from_secs(24 * 60 * 60);
+/// Mixed into a fixed seed for the interval RNG (the golden-ratio constant).
+const TIMING_SEED_SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Picks images from the indexed list according to the configured order.
+#[derive(Debug, Clone)]
+pub struct ImageSelector {
+    /// Full indexed image list.
+    all_images: Vec<PathBuf>,
+    /// Source folders used for grouping and schedule lookups.
+    folders: Vec<FolderSource>,
+    /// Time-of-day rules that narrow `all_images`; empty when disabled.
+    schedule: Vec<ScheduleRule>,
+    /// Location that resolves sunrise and sunset anchors.
+    location: GeoLocation,
+    /// Rule whose images are currently in `images`, if any.
+    active_rule: Option<usize>,
+    /// Images eligible right now (all images, or the active rule's subset).
+    images: Vec<PathBuf>,
+    /// Images grouped by the first source folder that contains them.
+    groups: Vec<FolderGroup>,
+    random_order: bool,
+    random_mode: RandomMode,
+    repeat_window: RepeatWindow,
+    seed: Option<u64>,
+    last: Option<PathBuf>,
+}
+
+/// Images owned by one source, with the weight used to pick the source.
+#[derive(Debug, Clone)]
+struct FolderGroup {
+    weight: u32,
+    members: Vec<PathBuf>,
+}
+
+/// Library state and images already chosen for the wallpaper being built.
+struct PickContext<'a> {
+    library: &'a ImageLibrary,
+    now: u64,
+    batch: &'a [PathBuf],
+}
+
+impl ImageSelector {
+    /// Build a selector over the config's already-sorted image list.
+    pub fn new(config: &SlideshowConfig) -> Self {
+        Self {
+            all_images: config.images.clone(),
+            folders: config.folders.clone(),
+            schedule: config.schedule.clone(),
+            location: config.location,
+            active_rule: None,
+            images: config.images.clone(),
+            groups: group_by_folder(&config.images, &config.folders),
+            random_order: config.random_order,
+            random_mode: config.random_mode,
+            repeat_window: config.repeat_window,
+            seed: config.seed,
+            last: config.current_images.last().cloned(),
+        }
+    }
+
+    /// Switch to the images of the rule active at `now`; returns true when it changed.
+    ///
+    /// Overlapping rules are tried in order and one whose folders hold no indexed
+    /// images is skipped; with no usable rule the whole library is shown, so the
+    /// slideshow never runs dry.
+    pub fn refresh_schedule(&mut self, now: u64) -> bool {
+        if self.schedule.is_empty() {
+            return false;
+        }
+        let active = schedule::active_rules(&self.schedule, self.location, now)
+            .into_iter()
+            .find_map(|idx| {
+                let images =
+                    schedule::rule_images(&self.schedule[idx], &self.all_images, &self.folders);
+                (!images.is_empty()).then_some((idx, images))
+            });
+        let active_rule = active.as_ref().map(|(idx, _)| *idx);
+        if active_rule == self.active_rule {
+            return false;
+        }
+
+        self.active_rule = active_rule;
+        self.images = match active {
+            Some((_, images)) => images,
+            None => self.all_images.clone(),
+        };
+        self.groups = group_by_folder(&self.images, &self.folders);
+        true
+    }
+
+    /// Time until the next schedule rule boundary, if a schedule is configured.
+    pub fn until_next_boundary(&self, now: u64) -> Option<Duration> {
+        schedule::until_next_boundary(&self.schedule, self.location, now)
+    }
+
+    /// Return true when picks depend only on the local date.
+    pub fn is_daily(&self) -> bool {
+        self.random_order && self.random_mode == RandomMode::Daily
+    }
+
+    /// Select `count` distinct images for one wallpaper change.
+    ///
+    /// `now` is Unix seconds and drives the schedule, daily pick, and repeat window.
+    pub fn pick_batch(
+        &mut self,
+        count: usize,
+        rng: &mut ChaChaRng,
+        library: &ImageLibrary,
+        now: u64,
+    ) -> Result<Vec<PathBuf>> {
+        self.refresh_schedule(now);
+        if self.is_daily() {
+            let batch = self.pick_daily(count, now);
+            self.last = batch.last().cloned();
+            return Ok(batch);
+        }
+
+        let mut batch = Vec::with_capacity(count);
+        for _ in 0..count {
+            let next = if self.random_order {
+                let ctx = PickContext {
+                    library,
+                    now,
+                    batch: &batch,
+                };
+                self.pick_random(rng, &ctx)?
+            } else {
+                sequential_pick(&self.images, self.last.as_ref())
+            };
+            self.last = Some(next.clone());
+            batch.push(next);
+        }
+        Ok(batch)
+    }
+
+    /// Dispatch to the configured random strategy.
+    fn pick_random(&self, rng: &mut ChaChaRng, ctx: &PickContext<'_>) -> Result<PathBuf> {
+        match self.random_mode {
+            RandomMode::Uniform => self
+                .eligible(&self.images, ctx)
+                .choose(rng)
+                .map(|path| (*path).clone())
+                .ok_or_else(|| anyhow!("no images available")),
+            RandomMode::ByFolder => self.pick_by_folder(rng, ctx),
+            RandomMode::Weighted => self.pick_weighted(rng, ctx),
+            RandomMode::Daily => self
+                .pick_daily(1, ctx.now)
+                .pop()
+                .ok_or_else(|| anyhow!("no images available")),
+        }
+    }
+
+    /// Pick the images for the local day containing `now`.
+    ///
+    /// The pick depends only on the seed, the date, and the sorted image list, so
+    /// machines with the same sources show the same wallpaper all day.
+    fn pick_daily(&self, count: usize, now: u64) -> Vec<PathBuf> {
+        let day = local_day_number(now);
+        let mut rng = ChaChaRng::seed_from_u64(daily_seed(self.seed.unwrap_or(0), day));
+        self.images
+            .choose_multiple(&mut rng, count)
+            .cloned()
+            .collect()
+    }
+
+    /// Pick a folder by weight, then a random image inside it.
+    fn pick_by_folder(&self, rng: &mut ChaChaRng, ctx: &PickContext<'_>) -> Result<PathBuf> {
+        let pools: Vec<Vec<&PathBuf>> = self
+            .groups
+            .iter()
+            .map(|group| self.fresh(&group.members, ctx))
+            .collect();
+        // Folders whose images are all inside the repeat window sit this round out.
+        let weights: Vec<u32> = self
+            .groups
+            .iter()
+            .zip(&pools)
+            .map(|(group, pool)| if pool.is_empty() { 0 } else { group.weight })
+            .collect();
+        let pool = match WeightedIndex::new(&weights) {
+            Ok(dist) => pools[rng.sample(dist)].clone(),
+            Err(_) => {
+                // All weights are zero: treat the folders that still have fresh images equally.
+                let open: Vec<&Vec<&PathBuf>> =
+                    pools.iter().filter(|pool| !pool.is_empty()).collect();
+                match open.choose(rng) {
+                    Some(pool) => (*pool).clone(),
+                    None => self.least_recent(&self.images, ctx),
+                }
+            }
+        };
+        pool.choose(rng)
+            .map(|path| (*path).clone())
+            .ok_or_else(|| anyhow!("no images available"))
+    }
+
+    /// Pick an image with probability proportional to its library weight.
+    fn pick_weighted(&self, rng: &mut ChaChaRng, ctx: &PickContext<'_>) -> Result<PathBuf> {
+        let pool = self.eligible(&self.images, ctx);
+        let weights: Vec<f64> = pool
+            .iter()
+            .map(|path| ctx.library.selection_weight(path))
+            .collect();
+        let dist = WeightedIndex::new(&weights).map_err(|err| anyhow!("{err}"))?;
+        Ok(pool[rng.sample(dist)].clone())
+    }
+
+    /// Fresh images from `pool`, or the least recently shown one when none are fresh.
+    fn eligible<'a>(&self, pool: &'a [PathBuf], ctx: &PickContext<'_>) -> Vec<&'a PathBuf> {
+        let fresh = self.fresh(pool, ctx);
+        if fresh.is_empty() {
+            self.least_recent(pool, ctx)
+        } else {
+            fresh
+        }
+    }
+
+    /// Images outside the repeat window that are not already in this batch.
+    fn fresh<'a>(&self, pool: &'a [PathBuf], ctx: &PickContext<'_>) -> Vec<&'a PathBuf> {
+        pool.iter()
+            .filter(|path| {
+                !ctx.batch.contains(path)
+                    && !ctx.library.is_recent(path, self.repeat_window, ctx.now)
+            })
+            .collect()
+    }
+
+    /// The image that has waited longest since it was shown, skipping the current batch.
+    fn least_recent<'a>(&self, pool: &'a [PathBuf], ctx: &PickContext<'_>) -> Vec<&'a PathBuf> {
+        let unbatched = pool
+            .iter()
+            .filter(|path| !ctx.batch.contains(path))
+            .min_by_key(|path| ctx.library.last_change(path));
+        // Only a pool smaller than the batch can exhaust every option.
+        unbatched.or_else(|| pool.first()).into_iter().collect()
+    }
+}
+
+/// Build the worker RNG, seeded when a fixed seed is configured.
+pub fn make_rng(seed: Option<u64>) -> ChaChaRng {
+    match seed {
+        Some(seed) => ChaChaRng::seed_from_u64(seed),
+        None => ChaChaRng::from_entropy(),
+    }
+}
+
+/// Build the RNG for random intervals.
+///
+/// A fixed seed is mixed first, so timing draws are independent of image picks.
+pub fn make_timing_rng(seed: Option<u64>) -> ChaChaRng {
+    make_rng(seed.map(|seed| seed ^ TIMING_SEED_SALT))
+}
+
+/// Time left until the local calendar day after `now` begins.
+pub fn until_next_local_day(now: u64) -> Duration {
+    let next_midnight = Local
+        .timestamp_opt(now as i64, 0)
+        .single()
+        .and_then(|local| local.date_naive().succ_opt())
+        .and_then(|tomorrow| tomorrow.and_hms_opt(0, 0, 0))
+        // DST can skip or repeat midnight; take the first instant that exists.
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest());
+    match next_midnight {
+        Some(next) => Duration::from_secs((next.timestamp() - now as i64).max(1) as u64),
+        None => ONE_DAY,
+    }
+}
+
+/// Local calendar day number (days since 0001-01-01) for a Unix timestamp.
+fn local_day_number(now: u64) -> i64 {
+    Local
+        .timestamp_opt(now as i64, 0)
+        .single()
+        .map(|local| local.date_naive().num_days_from_ce() as i64)
+        .unwrap_or(0)
+}
+
+/// Mix the user seed and the day so neighbouring seeds and days do not collide.
+fn daily_seed(seed: u64, day: i64) -> u64 {
+    seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ day as u64
+}
+
+/// Assign each image to the first folder that contains it; leftovers form their own group.
+fn group_by_folder(images: &[PathBuf], folders: &[FolderSource]) -> Vec<FolderGroup> {
+    let mut groups: Vec<FolderGroup> = folders
+        .iter()
+        .map(|folder| FolderGroup {
+            weight: folder.weight,
+            members: Vec::new(),
+        })
+        .collect();
+    // The single-image source is weighted like a default folder.
+    groups.push(FolderGroup {
+        weight: 1,
+        members: Vec::new(),
+    });
+
+    for path in images {
+        let owner = folders
+            .iter()
+            .position(|folder| folder.contains(path))
+            .unwrap_or(folders.len());
+        groups[owner].members.push(path.clone());
+    }
+
+    groups.retain(|group| !group.members.is_empty());
+    groups
+}
+
+/// Select the next image in order, wrapping at the end.
+fn sequential_pick(images: &[PathBuf], last: Option<&PathBuf>) -> PathBuf {
+    if images.is_empty() {
+        return PathBuf::new();
+    }
+    if let Some(last) = last
+        && let Some(pos) = images.iter().position(|p| p == last)
+    {
+        return images[(pos + 1) % images.len()].clone();
+    }
+    images[0].clone()
+}
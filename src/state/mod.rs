@@ -1,11 +1,27 @@
 //! Application runtime state derived from persisted settings.
 
 use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
 
 use crate::i18n::Language;
 use crate::image_ops::FolderSource;
-use crate::settings::{AppSettings, FolderSetting, StitchOrientation, ThemeMode};
-use crate::wallpaper::StyleMode;
+use crate::image_ops::collage::{MAX_COLLAGE_IMAGES, MIN_COLLAGE_IMAGES};
+use crate::image_ops::decor::StitchStyle;
+use crate::image_ops::layout::StitchLayout;
+use crate::image_ops::render::{Letterbox, RenderTarget};
+use crate::library::{RepeatWindow, SharedLibrary};
+use crate::monitor::{power::PowerMonitor, process::ProcessMonitor};
+use crate::schedule::cron::CronSchedule;
+use crate::session::Pin;
+use crate::settings::{
+    AppSettings, BatteryPolicy, CropMode, FolderSetting, GeoLocation, IntervalMode, LetterboxFill,
+    RandomMode, ResampleFilter, RotateDirection, ScheduleRule, SortMode, SpanDisplay,
+    StitchOrientation, StitchScaling, ThemeMode,
+};
+use crate::slideshow::SlideshowConfig;
+use crate::wallpaper::{StyleMode, screen_size};
 
 /// In-memory state that drives UI rendering and slideshow behavior.
 #[derive(Debug, Clone)]
@@ -16,14 +32,66 @@ pub struct AppState {
     pub single_image: Option<PathBuf>,
     /// Whether portrait images should be rotated to landscape.
     pub auto_rotate: bool,
+    /// Which way auto-rotate turns portrait images.
+    pub rotate_direction: RotateDirection,
     /// Whether to choose images randomly instead of sequential order.
     pub random_order: bool,
+    /// Image ordering used by sequential selection.
+    pub sort_mode: SortMode,
+    /// Draw strategy used by random selection.
+    pub random_mode: RandomMode,
+    /// Random selection skips images shown within this many days (0 disables).
+    pub repeat_window_days: u32,
+    /// Random selection skips images shown within this many changes (0 disables).
+    pub repeat_window_changes: u32,
+    /// Fixed RNG seed for reproducible sequences; `None` seeds from entropy.
+    pub seed: Option<u64>,
+    /// Whether schedule rules restrict which folders are used.
+    pub schedule_enabled: bool,
+    /// Time-of-day source rules; the first active rule wins.
+    pub schedule_rules: Vec<ScheduleRule>,
+    /// Location used for sunrise and sunset anchors.
+    pub location: GeoLocation,
     /// Slideshow interval in seconds.
     pub interval_secs: u64,
+    /// Whether the interval is fixed or drawn from a range.
+    pub interval_mode: IntervalMode,
+    /// Upper bound of the random interval in seconds.
+    pub interval_max_secs: u64,
+    /// Whether changes follow `cron_expression` instead of the interval.
+    pub cron_enabled: bool,
+    /// Five-field cron expression evaluated against local time.
+    pub cron_expression: String,
+    /// Whether waking the machine from sleep changes the wallpaper right away.
+    pub change_on_resume: bool,
+    /// What the slideshow does while on battery.
+    pub battery_policy: BatteryPolicy,
+    /// Interval multiplier used by the lengthen battery policy.
+    pub battery_interval_factor: u32,
+    /// Linux `power_supply` directory read for AC/battery state.
+    pub power_supply_root: String,
+    /// Application names that hold the current wallpaper while running.
+    pub pause_apps: Vec<String>,
+    /// Linux procfs directory scanned for running applications.
+    pub proc_root: String,
     /// Current UI language.
     pub language: Language,
     /// Current Windows wallpaper style.
     pub style: StyleMode,
+    /// Whether the app renders the final frame itself instead of leaving scaling to the OS.
+    pub prerender: bool,
+    /// Pre-render width in pixels; 0 detects the screen size.
+    pub render_width: u32,
+    /// Pre-render height in pixels; 0 detects the screen size.
+    pub render_height: u32,
+    /// Filter used when pre-rendering scales an image.
+    pub resample_filter: ResampleFilter,
+    /// Background around Fit/Center frames and inside stitch cells.
+    pub letterbox_fill: LetterboxFill,
+    /// RGB colour used by the solid letterbox fill.
+    pub letterbox_color: [u8; 3],
+    /// How Fill frames and stitched canvases are cropped.
+    pub crop_mode: CropMode,
     /// Current theme selection.
     pub theme: ThemeMode,
     /// Window opacity in range [0.3, 1.0].
@@ -36,12 +104,48 @@ pub struct AppState {
     pub stitch_count: u8,
     /// Orientation for stitching.
     pub stitch_orientation: StitchOrientation,
+    /// Name of the selected stitch layout; empty picks one from count and orientation.
+    pub stitch_layout: String,
+    /// Layouts the user defined.
+    pub stitch_layouts: Vec<StitchLayout>,
+    /// How stitched images are sized to their slots.
+    pub stitch_scaling: StitchScaling,
+    /// Gap between stitched images in pixels.
+    pub stitch_gap: u32,
+    /// Margin around a stitched composite in pixels.
+    pub stitch_margin: u32,
+    /// RGB colour of the gaps and margin.
+    pub stitch_border_color: [u8; 3],
+    /// Corner radius of stitched images in pixels.
+    pub stitch_corner_radius: u32,
+    /// Whether stitched images cast a shadow onto the background.
+    pub stitch_shadow: bool,
+    /// Whether wallpapers are collages of many images instead.
+    pub collage_enabled: bool,
+    /// Number of images in a collage.
+    pub collage_count: u32,
+    /// Whether Span wallpapers get a separate image on every display.
+    pub span_per_display: bool,
+    /// Monitors the spanned canvas is composed for.
+    pub span_displays: Vec<SpanDisplay>,
     /// Target width for cropping (always applied when stitching).
     pub stitch_crop_width: u32,
     /// Target height for cropping (always applied when stitching).
     pub stitch_crop_height: u32,
 }
 
+/// Settings that decide when the next change is due.
+///
+/// A restart that leaves these alone keeps the running countdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeTiming {
+    interval_secs: u64,
+    interval_mode: IntervalMode,
+    interval_max_secs: u64,
+    cron_expression: Option<String>,
+    folder_intervals: Vec<Option<u64>>,
+}
+
 impl AppState {
     /// Build the runtime state from persisted settings.
     pub fn from_settings(settings: &AppSettings) -> Self {
@@ -51,6 +155,8 @@ impl AppState {
             .map(|folder| FolderSource {
                 path: PathBuf::from(&folder.path),
                 include_subfolders: folder.include_subfolders,
+                weight: folder.weight,
+                interval_secs: folder.interval_secs,
             })
             .collect();
         let single_image = settings
@@ -62,16 +168,54 @@ impl AppState {
             folders,
             single_image,
             auto_rotate: settings.auto_rotate,
+            rotate_direction: settings.rotate_direction,
             random_order: settings.random_order,
+            sort_mode: settings.sort_mode,
+            random_mode: settings.random_mode,
+            repeat_window_days: settings.repeat_window_days,
+            repeat_window_changes: settings.repeat_window_changes,
+            seed: settings.seed,
+            schedule_enabled: settings.schedule_enabled,
+            schedule_rules: settings.schedule_rules.clone(),
+            location: settings.location,
             interval_secs: settings.interval_secs,
+            interval_mode: settings.interval_mode,
+            interval_max_secs: settings.interval_max_secs,
+            cron_enabled: settings.cron_enabled,
+            cron_expression: settings.cron_expression.clone(),
+            change_on_resume: settings.change_on_resume,
+            battery_policy: settings.battery_policy,
+            battery_interval_factor: settings.battery_interval_factor,
+            power_supply_root: settings.power_supply_root.clone(),
+            pause_apps: settings.pause_apps.clone(),
+            proc_root: settings.proc_root.clone(),
             language: settings.language,
             style: settings.style,
+            prerender: settings.prerender,
+            render_width: settings.render_width,
+            render_height: settings.render_height,
+            resample_filter: settings.resample_filter,
+            letterbox_fill: settings.letterbox_fill,
+            letterbox_color: settings.letterbox_color,
+            crop_mode: settings.crop_mode,
             theme: settings.theme,
             window_opacity: settings.window_opacity,
             running: settings.running,
             stitch_enabled: settings.stitch_enabled,
             stitch_count: settings.stitch_count,
             stitch_orientation: settings.stitch_orientation,
+            stitch_layout: settings.stitch_layout.clone(),
+            stitch_layouts: settings.stitch_layouts.clone(),
+            stitch_scaling: settings.stitch_scaling,
+            stitch_gap: settings.stitch_gap,
+            stitch_margin: settings.stitch_margin,
+            stitch_border_color: settings.stitch_border_color,
+            stitch_corner_radius: settings.stitch_corner_radius,
+            stitch_shadow: settings.stitch_shadow,
+            collage_enabled: settings.collage_enabled,
+            collage_count: settings.collage_count,
+            span_per_display: settings.span_per_display,
+            span_displays: settings.span_displays.clone(),
             stitch_crop_width: settings.stitch_crop_width,
             stitch_crop_height: settings.stitch_crop_height,
         }
@@ -85,6 +229,8 @@ impl AppState {
             .map(|folder| FolderSetting {
                 path: folder.path.to_string_lossy().to_string(),
                 include_subfolders: folder.include_subfolders,
+                weight: folder.weight,
+                interval_secs: folder.interval_secs,
             })
             .collect();
         settings.single_image = self
@@ -92,17 +238,211 @@ impl AppState {
             .as_ref()
             .map(|path| path.to_string_lossy().to_string());
         settings.auto_rotate = self.auto_rotate;
+        settings.rotate_direction = self.rotate_direction;
         settings.random_order = self.random_order;
+        settings.sort_mode = self.sort_mode;
+        settings.random_mode = self.random_mode;
+        settings.repeat_window_days = self.repeat_window_days;
+        settings.repeat_window_changes = self.repeat_window_changes;
+        settings.seed = self.seed;
+        settings.schedule_enabled = self.schedule_enabled;
+        settings.schedule_rules = self.schedule_rules.clone();
+        settings.location = self.location;
         settings.interval_secs = self.interval_secs;
+        settings.interval_mode = self.interval_mode;
+        settings.interval_max_secs = self.interval_max_secs;
+        settings.cron_enabled = self.cron_enabled;
+        settings.cron_expression = self.cron_expression.clone();
+        settings.change_on_resume = self.change_on_resume;
+        settings.battery_policy = self.battery_policy;
+        settings.battery_interval_factor = self.battery_interval_factor;
+        settings.power_supply_root = self.power_supply_root.clone();
+        settings.pause_apps = self.pause_apps.clone();
+        settings.proc_root = self.proc_root.clone();
         settings.language = self.language;
         settings.style = self.style;
+        settings.prerender = self.prerender;
+        settings.render_width = self.render_width;
+        settings.render_height = self.render_height;
+        settings.resample_filter = self.resample_filter;
+        settings.letterbox_fill = self.letterbox_fill;
+        settings.letterbox_color = self.letterbox_color;
+        settings.crop_mode = self.crop_mode;
         settings.theme = self.theme;
         settings.window_opacity = self.window_opacity;
         settings.running = self.running;
         settings.stitch_enabled = self.stitch_enabled;
         settings.stitch_count = self.stitch_count;
         settings.stitch_orientation = self.stitch_orientation;
+        settings.stitch_layout = self.stitch_layout.clone();
+        settings.stitch_layouts = self.stitch_layouts.clone();
+        settings.stitch_scaling = self.stitch_scaling;
+        settings.stitch_gap = self.stitch_gap;
+        settings.stitch_margin = self.stitch_margin;
+        settings.stitch_border_color = self.stitch_border_color;
+        settings.stitch_corner_radius = self.stitch_corner_radius;
+        settings.stitch_shadow = self.stitch_shadow;
+        settings.collage_enabled = self.collage_enabled;
+        settings.collage_count = self.collage_count;
+        settings.span_per_display = self.span_per_display;
+        settings.span_displays = self.span_displays.clone();
         settings.stitch_crop_width = self.stitch_crop_width;
         settings.stitch_crop_height = self.stitch_crop_height;
     }
+
+    /// Repeat window configured for random selection.
+    pub fn repeat_window(&self) -> RepeatWindow {
+        RepeatWindow {
+            days: self.repeat_window_days,
+            changes: self.repeat_window_changes,
+        }
+    }
+
+    /// Parsed cron schedule when cron timing is enabled.
+    pub fn cron_schedule(&self) -> Result<Option<CronSchedule>> {
+        if !self.cron_enabled {
+            return Ok(None);
+        }
+        CronSchedule::parse(&self.cron_expression).map(Some)
+    }
+
+    /// Timing settings a restart compares to carry over the planned change.
+    pub fn change_timing(&self) -> ChangeTiming {
+        ChangeTiming {
+            interval_secs: self.interval_secs,
+            interval_mode: self.interval_mode,
+            interval_max_secs: self.interval_max_secs,
+            cron_expression: self.cron_enabled.then(|| self.cron_expression.clone()),
+            folder_intervals: self
+                .folders
+                .iter()
+                .map(|folder| folder.interval_secs)
+                .collect(),
+        }
+    }
+
+    /// Frame to pre-render wallpapers into, when pre-rendering is on and a size is known.
+    pub fn render_target(&self) -> Option<RenderTarget> {
+        if !self.prerender {
+            return None;
+        }
+        let (width, height) = if self.render_width > 0 && self.render_height > 0 {
+            (self.render_width, self.render_height)
+        } else {
+            screen_size(self.style)?
+        };
+        Some(RenderTarget {
+            width,
+            height,
+            style: self.style,
+            filter: self.resample_filter,
+            letterbox: self.letterbox(),
+            crop: self.crop_mode,
+        })
+    }
+
+    /// Layout stitched wallpapers use: the selected one by name, or the built-in
+    /// one for the stitch count and orientation.
+    pub fn active_layout(&self) -> StitchLayout {
+        let automatic =
+            || StitchLayout::automatic(self.stitch_orientation, self.stitch_count as usize);
+        if self.stitch_layout.is_empty() {
+            return automatic();
+        }
+        self.stitch_layouts
+            .iter()
+            .cloned()
+            .chain(StitchLayout::presets())
+            .find(|layout| layout.name == self.stitch_layout)
+            .unwrap_or_else(automatic)
+    }
+
+    /// Gaps, margin and corners drawn between stitched images.
+    pub fn stitch_style(&self) -> StitchStyle {
+        StitchStyle {
+            gap: self.stitch_gap,
+            margin: self.stitch_margin,
+            color: self.stitch_border_color,
+            corner_radius: self.stitch_corner_radius,
+            shadow: self.stitch_shadow,
+        }
+    }
+
+    /// Background fill for uncovered frame areas.
+    pub fn letterbox(&self) -> Letterbox {
+        Letterbox {
+            fill: self.letterbox_fill,
+            color: self.letterbox_color,
+        }
+    }
+
+    /// Displays to compose a spanned wallpaper for, when Span gets one image per display.
+    pub fn span_layout(&self) -> Option<Vec<SpanDisplay>> {
+        (self.style == StyleMode::Span && self.span_per_display && !self.span_displays.is_empty())
+            .then(|| self.span_displays.clone())
+    }
+
+    /// Style handed to the OS; a pre-rendered frame must be shown without rescaling.
+    pub fn os_style(&self) -> StyleMode {
+        if self.render_target().is_some() {
+            self.style.for_prerendered()
+        } else {
+            self.style
+        }
+    }
+
+    /// Worker configuration over the indexed `images`.
+    ///
+    /// Timing is left to the interval; callers add the cron schedule and any deadline
+    /// resumed from the previous run.
+    pub fn slideshow_config(
+        &self,
+        images: Vec<PathBuf>,
+        current_images: Vec<PathBuf>,
+        pin: Option<Pin>,
+        library: SharedLibrary,
+    ) -> SlideshowConfig {
+        SlideshowConfig {
+            images,
+            folders: self.folders.clone(),
+            auto_rotate: self.auto_rotate.then_some(self.rotate_direction),
+            style: self.os_style(),
+            render: self.render_target(),
+            interval: Duration::from_secs(self.interval_secs),
+            interval_max: (self.interval_mode == IntervalMode::Random)
+                .then(|| Duration::from_secs(self.interval_max_secs)),
+            cron: None,
+            change_on_resume: self.change_on_resume,
+            resume_at: None,
+            current_images,
+            pin,
+            battery_policy: self.battery_policy,
+            battery_interval_factor: self.battery_interval_factor,
+            power: PowerMonitor::with_root(&self.power_supply_root),
+            processes: ProcessMonitor::new(&self.proc_root, &self.pause_apps),
+            random_order: self.random_order,
+            random_mode: self.random_mode,
+            repeat_window: self.repeat_window(),
+            seed: self.seed,
+            schedule: if self.schedule_enabled {
+                self.schedule_rules.clone()
+            } else {
+                Vec::new()
+            },
+            location: self.location,
+            stitch_enabled: self.stitch_enabled,
+            stitch_layout: self.active_layout(),
+            stitch_scaling: self.stitch_scaling,
+            stitch_style: self.stitch_style(),
+            collage: self.collage_enabled.then(|| {
+                (self.collage_count as usize).clamp(MIN_COLLAGE_IMAGES, MAX_COLLAGE_IMAGES)
+            }),
+            span_displays: self.span_layout(),
+            stitch_crop_width: self.stitch_crop_width,
+            stitch_crop_height: self.stitch_crop_height,
+            letterbox: self.letterbox(),
+            crop_mode: self.crop_mode,
+            library,
+        }
+    }
 }
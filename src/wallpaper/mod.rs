@@ -2,4 +2,4 @@
 
 pub mod wallpaper;
 
-pub use wallpaper::{StyleMode, set_wallpaper, set_wallpaper_style};
+pub use wallpaper::{StyleMode, screen_size, set_wallpaper, set_wallpaper_style};
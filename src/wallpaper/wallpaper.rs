@@ -6,6 +6,7 @@ use std::path::Path;
 
 use anyhow::{Result, anyhow};
 use windows::Win32::UI::WindowsAndMessaging::{
+    GetSystemMetrics, SM_CXSCREEN, SM_CXVIRTUALSCREEN, SM_CYSCREEN, SM_CYVIRTUALSCREEN,
     SPI_SETDESKWALLPAPER, SPIF_SENDCHANGE, SPIF_UPDATEINIFILE, SystemParametersInfoW,
 };
 use winreg::RegKey;
@@ -50,6 +51,29 @@ impl StyleMode {
             StyleMode::Span => "Span",
         }
     }
+
+    /// Style that shows a frame already rendered to the screen size pixel for pixel.
+    pub fn for_prerendered(self) -> StyleMode {
+        match self {
+            // Span keeps the frame stretched over every monitor.
+            StyleMode::Span => StyleMode::Span,
+            _ => StyleMode::Center,
+        }
+    }
+}
+
+/// Pixel size a wallpaper in `mode` covers: the primary monitor, or the whole
+/// virtual desktop for [`StyleMode::Span`].
+///
+/// Sizes are in the process's DPI scaling; set an explicit render size if they
+/// come out scaled.
+pub fn screen_size(mode: StyleMode) -> Option<(u32, u32)> {
+    let (x_metric, y_metric) = match mode {
+        StyleMode::Span => (SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN),
+        _ => (SM_CXSCREEN, SM_CYSCREEN),
+    };
+    let (width, height) = unsafe { (GetSystemMetrics(x_metric), GetSystemMetrics(y_metric)) };
+    (width > 0 && height > 0).then_some((width as u32, height as u32))
 }
 
 /// Apply the Windows registry values for the selected style.